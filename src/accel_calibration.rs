@@ -0,0 +1,435 @@
+use crate::{
+    axis_remap::round,
+    config::{decode_offsets, encode_offsets},
+    gyro_bias::OFFSETS_READ,
+    interface::{ReadData, WriteData},
+    sequence::Op,
+    AccelerometerRange, Bmi160, Data, Error, Register, Sensor3DData, SensorCalibration,
+    SensorSelector,
+};
+use embedded_hal::delay::DelayNs;
+
+/// Accelerometer offset register resolution in g
+pub(crate) const ACCEL_OFFSET_LSB_G: f32 = 0.0039;
+
+/// Sensor axis pointing up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Face {
+    /// X axis pointing up
+    XUp,
+    /// X axis pointing down
+    XDown,
+    /// Y axis pointing up
+    YUp,
+    /// Y axis pointing down
+    YDown,
+    /// Z axis pointing up
+    ZUp,
+    /// Z axis pointing down
+    ZDown,
+}
+
+impl Face {
+    /// All faces
+    pub const ALL: [Face; 6] = [
+        Face::XUp,
+        Face::XDown,
+        Face::YUp,
+        Face::YDown,
+        Face::ZUp,
+        Face::ZDown,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Face of a sample at rest, if one axis measures more than half the
+    /// gravity and the others less.
+    fn detect(sample: Sensor3DData, sensitivity: i32) -> Option<Face> {
+        let half_g = sensitivity / 2;
+        let v = [sample.x, sample.y, sample.z].map(i32::from);
+        let mut face = None;
+        for (axis, value) in v.iter().enumerate() {
+            if value.abs() > half_g {
+                if face.is_some() {
+                    return None;
+                }
+                face = Some(Face::ALL[2 * axis + usize::from(*value < 0)]);
+            }
+        }
+        face
+    }
+}
+
+/// Result of feeding a sample to [`SixPositionCalibration`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum CaptureStatus {
+    /// The sample was added to the current face average.
+    Collecting(Face),
+    /// The device moved. The current average was discarded.
+    Moving,
+    /// No axis is aligned with the gravity. The current average was discarded.
+    Tilted,
+    /// The average for the face is complete.
+    Captured(Face),
+}
+
+/// Accelerometer calibration from the six-position method
+///
+/// Each sample is in the sensor axes and the accelerometer range the
+/// calibration was created with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct AccelCalibration {
+    /// Zero-g offset per axis in g
+    pub bias: [f32; 3],
+    /// Measured sensitivity relative to the nominal sensitivity per axis
+    pub scale: [f32; 3],
+}
+
+impl AccelCalibration {
+    /// Accelerometer offset register values compensating the bias.
+    ///
+    /// Values are saturated to the register range of ±0.5 g. These are only
+    /// correct if the accelerometer offset compensation was disabled while
+    /// capturing. Otherwise use
+    /// [`compensate_accel_bias()`](crate::Bmi160::compensate_accel_bias),
+    /// which adds to the current offsets.
+    pub fn offsets(&self) -> [i8; 3] {
+        // `as` saturates to the i8 range
+        self.bias.map(|b| round(-b / ACCEL_OFFSET_LSB_G) as i8)
+    }
+
+    /// Host-side calibration compensating the bias and scale.
+    ///
+    /// Do not combine this with the [`offsets()`](Self::offsets) written to
+    /// the device, as the bias would be compensated twice.
+    pub fn sensor_calibration(&self) -> SensorCalibration {
+        let [x, y, z] = self.scale;
+        SensorCalibration {
+            bias: self.bias,
+            matrix: [
+                [1.0 / x, 0.0, 0.0],
+                [0.0, 1.0 / y, 0.0],
+                [0.0, 0.0, 1.0 / z],
+            ],
+        }
+    }
+}
+
+/// Six-position accelerometer calibration
+///
+/// The device is placed at rest with each of its axes pointing up and down.
+/// Samples are averaged per face and samples taken while moving are
+/// rejected. Once all six faces are captured, the bias and scale of each
+/// axis are solved.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SixPositionCalibration {
+    range: AccelerometerRange,
+    samples_per_face: u16,
+    motion_threshold: u16,
+    faces: [Option<[f32; 3]>; 6],
+    reference: Sensor3DData,
+    sum: [i32; 3],
+    count: u16,
+}
+
+impl SixPositionCalibration {
+    /// Create a new calibration.
+    ///
+    /// `samples_per_face` samples are averaged for each face. A sample
+    /// differing from the first sample of the average by more than
+    /// `motion_threshold` LSB on any axis is considered movement.
+    pub fn new(range: AccelerometerRange, samples_per_face: u16, motion_threshold: u16) -> Self {
+        SixPositionCalibration {
+            range,
+            samples_per_face: samples_per_face.max(1),
+            motion_threshold,
+            faces: [None; 6],
+            reference: Sensor3DData { x: 0, y: 0, z: 0 },
+            sum: [0; 3],
+            count: 0,
+        }
+    }
+
+    /// Add the accelerometer sample of the data, if any, and return the
+    /// face if it was captured.
+    pub(crate) fn add_data(&mut self, data: &Data) -> Option<Face> {
+        match data.accel.map(|accel| self.add_sample(accel)) {
+            Some(CaptureStatus::Captured(face)) => Some(face),
+            _ => None,
+        }
+    }
+
+    /// Add a raw accelerometer sample in the sensor axes.
+    pub fn add_sample(&mut self, sample: Sensor3DData) -> CaptureStatus {
+        let face = match Face::detect(sample, self.range.sensitivity()) {
+            Some(face) => face,
+            None => {
+                self.count = 0;
+                return CaptureStatus::Tilted;
+            }
+        };
+        if self.count == 0 {
+            self.reference = sample;
+            self.sum = [0; 3];
+        } else if self.is_moving(sample) {
+            self.count = 0;
+            return CaptureStatus::Moving;
+        }
+        let v = [sample.x, sample.y, sample.z];
+        for (sum, value) in self.sum.iter_mut().zip(v) {
+            *sum += i32::from(value);
+        }
+        self.count += 1;
+        if self.count < self.samples_per_face {
+            return CaptureStatus::Collecting(face);
+        }
+        let count = f32::from(self.count);
+        self.faces[face.index()] = Some(self.sum.map(|s| s as f32 / count));
+        self.count = 0;
+        CaptureStatus::Captured(face)
+    }
+
+    /// Whether the face has been captured
+    pub fn is_captured(&self, face: Face) -> bool {
+        self.faces[face.index()].is_some()
+    }
+
+    /// Whether all six faces have been captured
+    pub fn is_complete(&self) -> bool {
+        self.faces.iter().all(Option::is_some)
+    }
+
+    /// Solve the bias and scale of each axis.
+    ///
+    /// Returns `None` until all six faces have been captured.
+    pub fn solve(&self) -> Option<AccelCalibration> {
+        let sensitivity = self.range.sensitivity() as f32;
+        let mut result = AccelCalibration {
+            bias: [0.0; 3],
+            scale: [0.0; 3],
+        };
+        for axis in 0..3 {
+            let up = self.faces[2 * axis]?[axis];
+            let down = self.faces[2 * axis + 1]?[axis];
+            result.bias[axis] = (up + down) / 2.0 / sensitivity;
+            result.scale[axis] = (up - down) / 2.0 / sensitivity;
+        }
+        Some(result)
+    }
+
+    fn is_moving(&self, sample: Sensor3DData) -> bool {
+        let r = self.reference;
+        [(sample.x, r.x), (sample.y, r.y), (sample.z, r.z)]
+            .iter()
+            .any(|&(a, b)| a.abs_diff(b) > self.motion_threshold)
+    }
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Read accelerometer samples into the six-position calibration until
+    /// a face is captured or `max_samples` samples have been read.
+    ///
+    /// The samples are read in the sensor axes, ignoring the axis remapping,
+    /// every `interval_us` µs. Returns the captured face, if any.
+    ///
+    /// The samples include the accelerometer offset compensation if it is
+    /// enabled, so the resulting bias is the residual bias to pass to
+    /// [`compensate_accel_bias()`](Self::compensate_accel_bias).
+    pub fn capture_accel_face<D: DelayNs>(
+        &mut self,
+        calibration: &mut SixPositionCalibration,
+        delay: &mut D,
+        interval_us: u32,
+        max_samples: u32,
+    ) -> Result<Option<Face>, Error<CommE>> {
+        for _ in 0..max_samples {
+            let data = self.sensor_data(SensorSelector::new().accel())?;
+            if let Some(face) = calibration.add_data(&data) {
+                return Ok(Some(face));
+            }
+            delay.delay_us(interval_us);
+        }
+        Ok(None)
+    }
+
+    /// Compensate an accelerometer bias in g with the accelerometer offset
+    /// registers and enable the accelerometer offset compensation.
+    ///
+    /// The bias is subtracted from the current offsets, so a residual bias
+    /// captured with the offset compensation enabled can be passed, e.g.
+    /// [`AccelCalibration::bias`]. The bias must be in the sensor axes and
+    /// the offsets saturate at about ±0.5 g.
+    /// Returns the new offsets in units of 3.9 mg.
+    pub fn compensate_accel_bias(&mut self, bias: [f32; 3]) -> Result<[i8; 3], Error<CommE>> {
+        let mut data = [0; 7];
+        self.run([OFFSETS_READ], &mut data)?;
+        let (offsets, writes) = compensated_accel_offsets(&data, bias)?;
+        self.run(writes, &mut [])?;
+        Ok(offsets)
+    }
+}
+
+/// Register writes setting the accelerometer offsets
+type AccelOffsetWrites = [Op; 4];
+
+/// New accelerometer offsets and the register writes setting them
+pub(crate) fn compensated_accel_offsets<E>(
+    registers: &[u8],
+    bias: [f32; 3],
+) -> Result<([i8; 3], AccelOffsetWrites), Error<E>> {
+    let mut offsets = decode_offsets(registers);
+    let current = if offsets.accel_enabled {
+        offsets.accel
+    } else {
+        [0; 3]
+    };
+    for axis in 0..3 {
+        // `as` saturates to the i16 range
+        let correction = round(bias[axis] / ACCEL_OFFSET_LSB_G) as i16;
+        let offset = i16::from(current[axis]).saturating_sub(correction);
+        offsets.accel[axis] = offset.clamp(-128, 127) as i8;
+    }
+    offsets.accel_enabled = true;
+    let values = encode_offsets(&offsets)?;
+    let writes = [
+        Op::Write(Register::OFFSET_0, values[0]),
+        Op::Write(Register::OFFSET_0 + 1, values[1]),
+        Op::Write(Register::OFFSET_0 + 2, values[2]),
+        Op::Write(Register::OFFSET_6, values[6]),
+    ];
+    Ok((offsets.accel, writes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(x: i16, y: i16, z: i16) -> Sensor3DData {
+        Sensor3DData { x, y, z }
+    }
+
+    #[test]
+    fn can_detect_faces() {
+        assert_eq!(
+            Face::detect(sample(16000, 100, -200), 16384),
+            Some(Face::XUp)
+        );
+        assert_eq!(Face::detect(sample(0, 0, -16384), 16384), Some(Face::ZDown));
+        assert_eq!(Face::detect(sample(0, -9000, 0), 16384), Some(Face::YDown));
+        assert_eq!(Face::detect(sample(11585, 11585, 0), 16384), None);
+        assert_eq!(Face::detect(sample(0, 0, 100), 16384), None);
+    }
+
+    #[test]
+    fn rejects_movement() {
+        let mut cal = SixPositionCalibration::new(AccelerometerRange::G2, 3, 50);
+        let status = cal.add_sample(sample(0, 0, 16384));
+        assert_eq!(status, CaptureStatus::Collecting(Face::ZUp));
+        assert_eq!(cal.add_sample(sample(0, 60, 16384)), CaptureStatus::Moving);
+        assert_eq!(
+            cal.add_sample(sample(0, 0, 16384)),
+            CaptureStatus::Collecting(Face::ZUp)
+        );
+        assert_eq!(
+            cal.add_sample(sample(0, 40, 16384)),
+            CaptureStatus::Collecting(Face::ZUp)
+        );
+        assert_eq!(
+            cal.add_sample(sample(0, 0, 16390)),
+            CaptureStatus::Captured(Face::ZUp)
+        );
+        assert!(cal.is_captured(Face::ZUp));
+        assert!(!cal.is_complete());
+    }
+
+    #[test]
+    fn discards_average_when_tilted() {
+        let mut cal = SixPositionCalibration::new(AccelerometerRange::G2, 2, 50);
+        cal.add_sample(sample(16384, 0, 0));
+        assert_eq!(cal.add_sample(sample(0, 0, 0)), CaptureStatus::Tilted);
+        assert_eq!(
+            cal.add_sample(sample(16384, 0, 0)),
+            CaptureStatus::Collecting(Face::XUp)
+        );
+    }
+
+    #[test]
+    fn can_solve() {
+        let mut cal = SixPositionCalibration::new(AccelerometerRange::G2, 2, 50);
+        // x: bias 0.01 g, scale 1.02; y: bias -0.02 g, scale 0.98;
+        // z: bias 0.1 g, scale 1
+        let bias = [164, -328, 1638];
+        let gain = [16712, 16056, 16384];
+        for (i, face) in Face::ALL.into_iter().enumerate() {
+            let axis = i / 2;
+            let sign = if i % 2 == 0 { 1 } else { -1 };
+            let mut v = bias;
+            v[axis] += sign * gain[axis];
+            let s = sample(v[0], v[1], v[2]);
+            cal.add_sample(s);
+            assert_eq!(cal.add_sample(s), CaptureStatus::Captured(face));
+        }
+        assert!(cal.is_complete());
+        let result = cal.solve().unwrap();
+        let expected_bias = bias.map(|b| b as f32 / 16384.0);
+        let expected_scale = gain.map(|g| g as f32 / 16384.0);
+        for axis in 0..3 {
+            assert!((result.bias[axis] - expected_bias[axis]).abs() < 1e-6);
+            assert!((result.scale[axis] - expected_scale[axis]).abs() < 1e-6);
+        }
+        assert_eq!(result.offsets(), [-3, 5, -26]);
+        let calibration = result.sensor_calibration();
+        assert_eq!(calibration.bias, result.bias);
+        assert!((calibration.matrix[0][0] * result.scale[0] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn offsets_saturate() {
+        let result = AccelCalibration {
+            bias: [1.0, -1.0, 0.0],
+            scale: [1.0; 3],
+        };
+        assert_eq!(result.offsets(), [-128, 127, 0]);
+    }
+
+    #[test]
+    fn cannot_solve_incomplete() {
+        let cal = SixPositionCalibration::new(AccelerometerRange::G2, 2, 50);
+        assert_eq!(cal.solve(), None);
+    }
+
+    #[test]
+    fn adds_to_enabled_accel_offsets() {
+        let registers = [10, 0xF6, 120, 0, 0, 0, 0b0100_0000];
+        let (offsets, writes) =
+            compensated_accel_offsets::<()>(&registers, [0.039, -0.039, -0.039]).unwrap();
+        assert_eq!(offsets, [0, 0, 127]);
+        assert_eq!(
+            writes,
+            [
+                Op::Write(Register::OFFSET_0, 0),
+                Op::Write(Register::OFFSET_0 + 1, 0),
+                Op::Write(Register::OFFSET_0 + 2, 127),
+                Op::Write(Register::OFFSET_6, 0b0100_0000),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_disabled_accel_offsets() {
+        let registers = [10, 0, 0, 0, 0, 0, 0b1000_0000];
+        let (offsets, writes) =
+            compensated_accel_offsets::<()>(&registers, [0.0, 0.0078, 0.0]).unwrap();
+        assert_eq!(offsets, [0, -2, 0]);
+        assert_eq!(writes[3], Op::Write(Register::OFFSET_6, 0b1100_0000));
+    }
+}
@@ -0,0 +1,676 @@
+use crate::{
+    accel_calibration::compensated_accel_offsets,
+    config::{configure_ops, ConfigRegisters, CONFIG_READS, CONFIG_READ_LEN},
+    device_impl::{
+        accel_power_mode_cmd, check_chip_id, decode_interface_config, decode_power_mode,
+        decode_status, gyro_power_mode_cmd, interface_config_ops, magnet_power_mode_cmd,
+    },
+    fifo_impl::{
+        decode_fifo_length, fifo_config_ops, fifo_read_len, FIFO_FLUSH_CMD, FIFO_LENGTH_READ,
+    },
+    gyro_bias::{compensated_gyro_offsets, OFFSETS_READ},
+    interface::{AsyncReadData, AsyncWriteData, I2cInterface, SpiInterface},
+    interrupt_impl::{disable_interrupt_op, enable_interrupt_ops, interrupt_pin_config_op},
+    magnet_interface::{
+        magnet_address_op, magnet_config_ops, magnet_interface_op, magnet_manual_mode_op,
+        magnet_setup_ops,
+    },
+    magnetometer::{AuxMagnetometer, Bmm150, Conversion, MAX_CALIBRATION_LEN},
+    read_sensor_data::{
+        check_period_ticks, data_read, get_data, scale_data, scale_data_milli, scale_data_si,
+        SampleCollector, DATA_LEN, EMPTY_DATA,
+    },
+    register_dump::{RegisterDump, DUMP_READS, DUMP_READ_LEN},
+    sequence::{take_output, NoDelay, Op, MAGNET_OP_MAX_POLLS, MAX_READ_LEN},
+    AccelerometerPowerMode, AccelerometerRange, AxisRemap, BitFlags, Bmi160Async, Bmm150Preset,
+    Calibration, ClockSync, Config, Data, DataMilli, DataScaled, DataSi, Error, Face, FifoConfig,
+    GyroBiasEstimator, GyroscopePowerMode, GyroscopeRange, InterfaceConfig, InterruptPin,
+    InterruptPinConfig, InterruptSource, InvalidAxisRemap, MagnetometerConfig,
+    MagnetometerPowerMode, Register, SampleReport, SensorPowerMode, SensorSelector, SensorTime,
+    SixPositionCalibration, SlaveAddr, Status,
+};
+use embedded_hal_async::{delay::DelayNs, i2c::I2c, spi::SpiDevice};
+
+impl<I2C> Bmi160Async<I2cInterface<I2C>> {
+    /// Create new instance of the BMI160 device communicating through I2C.
+    pub fn new_with_i2c(i2c: I2C, address: SlaveAddr) -> Self {
+        Bmi160Async {
+            iface: I2cInterface {
+                i2c,
+                address: address.addr(),
+            },
+            accel_range: AccelerometerRange::default(),
+            gyro_range: GyroscopeRange::default(),
+            magnet_conversion: None,
+            axis_remap: AxisRemap::IDENTITY,
+            calibration: Calibration::IDENTITY,
+        }
+    }
+
+    /// Destroy driver instance, return I2C bus.
+    pub fn destroy(self) -> I2C {
+        self.iface.i2c
+    }
+}
+
+impl<SPI> Bmi160Async<SpiInterface<SPI>> {
+    /// Create new instance of the BMI160 device communicating through SPI.
+    ///
+    /// The device starts in I2C mode after power-up. Call
+    /// [`init()`](Self::init) before any other method to switch it to SPI.
+    pub fn new_with_spi(spi: SPI) -> Self {
+        Bmi160Async {
+            iface: SpiInterface { spi },
+            accel_range: AccelerometerRange::default(),
+            gyro_range: GyroscopeRange::default(),
+            magnet_conversion: None,
+            axis_remap: AxisRemap::IDENTITY,
+            calibration: Calibration::IDENTITY,
+        }
+    }
+
+    /// Destroy driver instance, return SPI device instance.
+    pub fn destroy(self) -> SPI {
+        self.iface.spi
+    }
+}
+
+impl<I2C, E> Bmi160Async<I2cInterface<I2C>>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Verify the chip ID.
+    ///
+    /// Returns `Error::InvalidChipId` if the device is not a BMI160.
+    pub async fn init(&mut self) -> Result<(), Error<E>> {
+        check_chip_id(self.chip_id().await?)
+    }
+}
+
+impl<SPI, E> Bmi160Async<SpiInterface<SPI>>
+where
+    SPI: SpiDevice<u8, Error = E>,
+{
+    /// Switch the device to SPI mode and verify the chip ID.
+    ///
+    /// See [`Bmi160::init()`](crate::Bmi160::init).
+    pub async fn init(&mut self) -> Result<(), Error<E>> {
+        self.iface.read_register(Register::SPI_COMM_TEST).await?;
+        check_chip_id(self.chip_id().await?)
+    }
+}
+
+impl<DI, CommE> Bmi160Async<DI>
+where
+    DI: AsyncReadData<Error = Error<CommE>> + AsyncWriteData<Error = Error<CommE>>,
+{
+    /// Get chip ID
+    pub async fn chip_id(&mut self) -> Result<u8, Error<CommE>> {
+        self.iface.read_register(Register::CHIPID).await
+    }
+
+    /// Get sensor power mode
+    pub async fn power_mode(&mut self) -> Result<SensorPowerMode, Error<CommE>> {
+        let status = self.iface.read_register(Register::PMU_STATUS).await?;
+        Ok(decode_power_mode(status))
+    }
+
+    /// Get sensor status
+    pub async fn status(&mut self) -> Result<Status, Error<CommE>> {
+        let status = self.iface.read_register(Register::STATUS).await?;
+        Ok(decode_status(status))
+    }
+
+    /// Configure accelerometer power mode
+    pub async fn set_accel_power_mode(
+        &mut self,
+        mode: AccelerometerPowerMode,
+    ) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::CMD, accel_power_mode_cmd(mode))
+            .await
+    }
+
+    /// Configure gyroscope power mode
+    pub async fn set_gyro_power_mode(
+        &mut self,
+        mode: GyroscopePowerMode,
+    ) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::CMD, gyro_power_mode_cmd(mode))
+            .await
+    }
+
+    /// Configure magnetometer power mode
+    pub async fn set_magnet_power_mode(
+        &mut self,
+        mode: MagnetometerPowerMode,
+    ) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::CMD, magnet_power_mode_cmd(mode))
+            .await
+    }
+
+    /// Set the accelerometer range
+    pub async fn set_accel_range(&mut self, range: AccelerometerRange) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::ACC_RANGE, range as u8)
+            .await?;
+        self.accel_range = range;
+        Ok(())
+    }
+
+    /// Set the gyro range
+    pub async fn set_gyro_range(&mut self, range: GyroscopeRange) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::GYR_RANGE, range as u8)
+            .await?;
+        self.gyro_range = range;
+        Ok(())
+    }
+
+    /// Get the primary interface configuration
+    pub async fn interface_config(&mut self) -> Result<InterfaceConfig, Error<CommE>> {
+        let if_conf = self.iface.read_register(Register::IF_CONF).await?;
+        let nv_conf = self.iface.read_register(Register::NV_CONF).await?;
+        Ok(decode_interface_config(if_conf, nv_conf))
+    }
+
+    /// Configure the primary interface: 3-wire SPI and I2C watchdog.
+    ///
+    /// The secondary interface mode is kept.
+    pub async fn set_interface_config(
+        &mut self,
+        config: InterfaceConfig,
+    ) -> Result<(), Error<CommE>> {
+        self.run(interface_config_ops(&config), &mut []).await
+    }
+
+    /// Write the whole device configuration.
+    ///
+    /// See [`Bmi160::configure()`](crate::Bmi160::configure).
+    pub async fn configure<D: DelayNs>(
+        &mut self,
+        config: &Config,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(configure_ops(config)?, delay, &mut [])
+            .await?;
+        self.accel_range = config.accel_range;
+        self.gyro_range = config.gyro_range;
+        Ok(())
+    }
+
+    /// Read the whole device configuration.
+    ///
+    /// See [`Bmi160::read_config()`](crate::Bmi160::read_config).
+    pub async fn read_config(&mut self) -> Result<Config, Error<CommE>> {
+        let mut data = [0; CONFIG_READ_LEN];
+        self.run(CONFIG_READS, &mut data).await?;
+        let config = ConfigRegisters::from_reads(&data).decode()?;
+        self.accel_range = config.accel_range;
+        self.gyro_range = config.gyro_range;
+        Ok(config)
+    }
+
+    /// Read the whole register map.
+    ///
+    /// See [`Bmi160::dump_registers()`](crate::Bmi160::dump_registers).
+    pub async fn dump_registers(&mut self) -> Result<RegisterDump, Error<CommE>> {
+        let mut data = [0; DUMP_READ_LEN];
+        self.run(DUMP_READS, &mut data).await?;
+        Ok(RegisterDump::from_reads(&data))
+    }
+
+    /// Write the configuration registers of a dump back to the device.
+    ///
+    /// See [`Bmi160::restore_registers()`](crate::Bmi160::restore_registers).
+    pub async fn restore_registers<D: DelayNs>(
+        &mut self,
+        dump: &RegisterDump,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(dump.restore_ops(), delay, &mut []).await?;
+        let (accel_range, gyro_range) = dump.ranges();
+        self.accel_range = accel_range.unwrap_or(self.accel_range);
+        self.gyro_range = gyro_range.unwrap_or(self.gyro_range);
+        Ok(())
+    }
+
+    /// Read latest sensor data
+    ///
+    /// See [`Bmi160::data()`](crate::Bmi160::data).
+    pub async fn data(&mut self, selector: SensorSelector) -> Result<Data, Error<CommE>> {
+        let raw_data = self.sensor_data(selector).await?;
+        Ok(self.axis_remap.remap_data(raw_data))
+    }
+
+    /// Read consecutive samples into `samples` as fast as the bus allows.
+    ///
+    /// See [`Bmi160::read_samples()`](crate::Bmi160::read_samples).
+    pub async fn read_samples(
+        &mut self,
+        selector: SensorSelector,
+        samples: &mut [Data],
+        period_ticks: u32,
+        max_polls: u32,
+    ) -> Result<SampleReport, Error<CommE>> {
+        check_period_ticks(period_ticks)?;
+        let selector = selector.time();
+        let mut collector = SampleCollector::new(samples, period_ticks, max_polls);
+        while collector.needs_poll() {
+            if collector.poll(&self.status().await?, selector) {
+                let data = self.data(selector).await?;
+                collector.add(data);
+            }
+        }
+        Ok(collector.report())
+    }
+
+    /// Read latest sensor data and scale it using the gyroscope and accelerometer ranges
+    ///
+    /// The magnetometer data is converted to µT if a magnetometer was set up
+    /// with [`setup_magnetometer()`](Self::setup_magnetometer). Otherwise it
+    /// is not available.
+    pub async fn data_scaled(
+        &mut self,
+        selector: SensorSelector,
+    ) -> Result<DataScaled, Error<CommE>> {
+        let raw_data = self.sensor_data(selector).await?;
+        Ok(self.scale_data(raw_data))
+    }
+
+    /// Scale raw data, e.g. samples decoded with the [`fifo`](crate::fifo)
+    /// module, like [`data_scaled()`](Self::data_scaled).
+    ///
+    /// See [`Bmi160::scale_data()`](crate::Bmi160::scale_data).
+    pub fn scale_data(&self, data: Data) -> DataScaled {
+        scale_data(
+            data,
+            self.accel_range,
+            self.gyro_range,
+            self.magnet_conversion,
+            &self.axis_remap,
+            &self.calibration,
+        )
+    }
+
+    /// Read latest sensor data in physical units
+    ///
+    /// See [`Bmi160::data_si()`](crate::Bmi160::data_si).
+    pub async fn data_si(&mut self, selector: SensorSelector) -> Result<DataSi, Error<CommE>> {
+        let raw_data = self.sensor_data(selector).await?;
+        Ok(scale_data_si(
+            raw_data,
+            self.accel_range,
+            self.gyro_range,
+            self.magnet_conversion,
+            &self.axis_remap,
+            &self.calibration,
+        ))
+    }
+
+    /// Read latest sensor data scaled to milli-g and milli-°/s with integer math
+    ///
+    /// See [`Bmi160::data_milli()`](crate::Bmi160::data_milli).
+    pub async fn data_milli(
+        &mut self,
+        selector: SensorSelector,
+    ) -> Result<DataMilli, Error<CommE>> {
+        let selector = SensorSelector {
+            magnet: false,
+            ..selector
+        };
+        let raw_data = self.sensor_data(selector).await?;
+        Ok(scale_data_milli(
+            raw_data,
+            self.accel_range,
+            self.gyro_range,
+            &self.axis_remap,
+        ))
+    }
+
+    /// Read latest sensor data in the sensor axes
+    async fn sensor_data(&mut self, selector: SensorSelector) -> Result<Data, Error<CommE>> {
+        if selector == SensorSelector::new() {
+            return Ok(EMPTY_DATA);
+        }
+        let (read, offset) = data_read(selector);
+        let mut data = [0; DATA_LEN];
+        self.run([read], &mut data).await?;
+        Ok(get_data(selector, &data, offset))
+    }
+
+    /// Set the transformation from the sensor axes to the board axes.
+    ///
+    /// See [`Bmi160::set_axis_remap()`](crate::Bmi160::set_axis_remap).
+    pub fn set_axis_remap(&mut self, remap: AxisRemap) -> Result<(), InvalidAxisRemap> {
+        if !remap.is_valid() {
+            return Err(InvalidAxisRemap);
+        }
+        self.axis_remap = remap;
+        Ok(())
+    }
+
+    /// Get the transformation from the sensor axes to the board axes.
+    pub fn axis_remap(&self) -> AxisRemap {
+        self.axis_remap
+    }
+
+    /// Set the host-side calibration applied to the scaled data.
+    ///
+    /// See [`Bmi160::set_calibration()`](crate::Bmi160::set_calibration).
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Get the host-side calibration.
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+
+    /// Read the accelerometer and gyroscope and add the sample to the bias
+    /// estimator.
+    ///
+    /// See [`Bmi160::update_gyro_bias()`](crate::Bmi160::update_gyro_bias).
+    pub async fn update_gyro_bias(
+        &mut self,
+        estimator: &mut GyroBiasEstimator,
+    ) -> Result<bool, Error<CommE>> {
+        let data = self
+            .sensor_data(SensorSelector::new().accel().gyro())
+            .await?;
+        Ok(estimator.update_raw(data, self.accel_range, self.gyro_range))
+    }
+
+    /// Compensate a gyroscope bias in °/s with the gyroscope offset registers
+    /// and enable the gyroscope offset compensation.
+    ///
+    /// See [`Bmi160::compensate_gyro_bias()`](crate::Bmi160::compensate_gyro_bias).
+    pub async fn compensate_gyro_bias(&mut self, bias: [f32; 3]) -> Result<[i16; 3], Error<CommE>> {
+        let mut data = [0; 7];
+        self.run([OFFSETS_READ], &mut data).await?;
+        let (offsets, writes) = compensated_gyro_offsets(&data, bias)?;
+        self.run(writes, &mut []).await?;
+        Ok(offsets)
+    }
+
+    /// Compensate an accelerometer bias in g with the accelerometer offset
+    /// registers and enable the accelerometer offset compensation.
+    ///
+    /// See [`Bmi160::compensate_accel_bias()`](crate::Bmi160::compensate_accel_bias).
+    pub async fn compensate_accel_bias(&mut self, bias: [f32; 3]) -> Result<[i8; 3], Error<CommE>> {
+        let mut data = [0; 7];
+        self.run([OFFSETS_READ], &mut data).await?;
+        let (offsets, writes) = compensated_accel_offsets(&data, bias)?;
+        self.run(writes, &mut []).await?;
+        Ok(offsets)
+    }
+
+    /// Read the sensor time and add it to the synchronizer paired with the
+    /// host time in µs returned by `host_micros` before and after the read.
+    ///
+    /// See [`Bmi160::sync_clock()`](crate::Bmi160::sync_clock).
+    pub async fn sync_clock<const N: usize, F: FnMut() -> u64>(
+        &mut self,
+        sync: &mut ClockSync<N>,
+        mut host_micros: F,
+    ) -> Result<SensorTime, Error<CommE>> {
+        let before = host_micros();
+        let data = self.sensor_data(SensorSelector::new().time()).await?;
+        let after = host_micros();
+        let time = SensorTime(data.time.unwrap_or_default());
+        sync.add_pair(before, after, time);
+        Ok(time)
+    }
+
+    /// Read the magnetometer and add the sample to the calibrator.
+    ///
+    /// See [`Bmi160::collect_magnet_sample()`](crate::Bmi160::collect_magnet_sample).
+    #[cfg(feature = "calibration")]
+    pub async fn collect_magnet_sample<const N: usize>(
+        &mut self,
+        calibrator: &mut crate::MagnetCalibrator<N>,
+    ) -> Result<bool, Error<CommE>> {
+        let conversion = self.magnet_conversion.ok_or(Error::InvalidInputData)?;
+        let data = self.sensor_data(SensorSelector::new().magnet()).await?;
+        match data.magnet {
+            Some(d) => Ok(calibrator.add_sample(conversion.convert(&d))),
+            None => Ok(false),
+        }
+    }
+
+    /// Read accelerometer samples into the six-position calibration until
+    /// a face is captured or `max_samples` samples have been read.
+    ///
+    /// See [`Bmi160::capture_accel_face()`](crate::Bmi160::capture_accel_face).
+    pub async fn capture_accel_face<D: DelayNs>(
+        &mut self,
+        calibration: &mut SixPositionCalibration,
+        delay: &mut D,
+        interval_us: u32,
+        max_samples: u32,
+    ) -> Result<Option<Face>, Error<CommE>> {
+        for _ in 0..max_samples {
+            let data = self.sensor_data(SensorSelector::new().accel()).await?;
+            if let Some(face) = calibration.add_data(&data) {
+                return Ok(Some(face));
+            }
+            delay.delay_us(interval_us).await;
+        }
+        Ok(None)
+    }
+
+    /// Enable the secondary magnetometer interface.
+    ///
+    /// See [`Bmi160::enable_magnet_interface()`](crate::Bmi160::enable_magnet_interface).
+    pub async fn enable_magnet_interface(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_interface_op()], &mut []).await
+    }
+
+    /// Set the 7-bit I2C address of the device connected to the
+    /// secondary magnetometer interface.
+    ///
+    /// Returns `Error::InvalidInputData` if the address does not fit in 7 bits.
+    pub async fn set_magnet_interface_address(&mut self, address: u8) -> Result<(), Error<CommE>> {
+        self.run([magnet_address_op(address)?], &mut []).await
+    }
+
+    /// Enable magnetometer interface manual mode (setup mode).
+    pub async fn enable_magnet_manual_mode(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_manual_mode_op(true)], &mut []).await
+    }
+
+    /// Disable magnetometer interface manual mode (switch to data mode).
+    pub async fn disable_magnet_manual_mode(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_manual_mode_op(false)], &mut []).await
+    }
+
+    /// Set the magnetometer output data rate, read burst length and
+    /// trigger-readout offset used in data mode.
+    ///
+    /// Returns `Error::InvalidInputData` if the offset does not fit in 4 bits
+    /// or if it is not shorter than the output data rate period.
+    pub async fn set_magnet_config(
+        &mut self,
+        config: MagnetometerConfig,
+    ) -> Result<(), Error<CommE>> {
+        self.run(magnet_config_ops(&config)?, &mut []).await
+    }
+
+    /// Read a register of the device connected to the secondary
+    /// magnetometer interface.
+    ///
+    /// Manual mode must be enabled. This waits until the manual operation
+    /// is finished and returns `Error::Timeout` if it does not finish.
+    pub async fn read_magnet_register(&mut self, register: u8) -> Result<u8, Error<CommE>> {
+        let mut value = [0];
+        self.run([Op::ReadMagnet(register)], &mut value).await?;
+        Ok(value[0])
+    }
+
+    /// Write a register of the device connected to the secondary
+    /// magnetometer interface.
+    ///
+    /// Manual mode must be enabled. This waits until the manual operation
+    /// is finished and returns `Error::Timeout` if it does not finish.
+    pub async fn write_magnet_register(
+        &mut self,
+        register: u8,
+        data: u8,
+    ) -> Result<(), Error<CommE>> {
+        self.run([Op::WriteMagnet(register, data)], &mut []).await
+    }
+
+    /// Set up a magnetometer connected to the secondary interface.
+    ///
+    /// See [`Bmi160::setup_magnetometer()`](crate::Bmi160::setup_magnetometer).
+    pub async fn setup_magnetometer<M: AuxMagnetometer, D: DelayNs>(
+        &mut self,
+        magnetometer: &M,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        let mut calibration = [0; MAX_CALIBRATION_LEN];
+        let (ops, calibration_len) = magnet_setup_ops(magnetometer)?;
+        self.run_delayed(ops, delay, &mut calibration).await?;
+        self.magnet_conversion = Some(Conversion::new::<M>(&calibration[..calibration_len]));
+        Ok(())
+    }
+
+    /// Set up a BMM150 magnetometer connected to the secondary interface.
+    ///
+    /// This is equivalent to calling [`setup_magnetometer()`](Self::setup_magnetometer)
+    /// with a [`Bmm150`] using the given preset.
+    pub async fn setup_bmm150<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        preset: Bmm150Preset,
+    ) -> Result<(), Error<CommE>> {
+        self.setup_magnetometer(&Bmm150::new(preset), delay).await
+    }
+
+    /// Configure which data is stored in the FIFO and its watermark level.
+    ///
+    /// The FIFO is operated in header mode.
+    pub async fn set_fifo_config(&mut self, config: FifoConfig) -> Result<(), Error<CommE>> {
+        self.run(fifo_config_ops(&config), &mut []).await
+    }
+
+    /// Clear the FIFO content.
+    pub async fn flush_fifo(&mut self) -> Result<(), Error<CommE>> {
+        self.iface
+            .write_register(Register::CMD, FIFO_FLUSH_CMD)
+            .await
+    }
+
+    /// Get the current FIFO fill level in bytes.
+    pub async fn fifo_length(&mut self) -> Result<u16, Error<CommE>> {
+        let mut data = [0; 2];
+        self.run([FIFO_LENGTH_READ], &mut data).await?;
+        Ok(decode_fifo_length(&data))
+    }
+
+    /// Read the FIFO content into the buffer and return the bytes read.
+    ///
+    /// See [`Bmi160::read_fifo()`](crate::Bmi160::read_fifo).
+    pub async fn read_fifo<'b>(&mut self, buffer: &'b mut [u8]) -> Result<&'b [u8], Error<CommE>> {
+        let len = fifo_read_len(self.fifo_length().await?, buffer.len())?;
+        self.iface
+            .read_burst(Register::FIFO_DATA, &mut buffer[..len])
+            .await?;
+        Ok(&buffer[..len])
+    }
+
+    /// Configure the electrical behavior of an interrupt pin.
+    pub async fn set_interrupt_pin_config(
+        &mut self,
+        pin: InterruptPin,
+        config: InterruptPinConfig,
+    ) -> Result<(), Error<CommE>> {
+        self.run([interrupt_pin_config_op(pin, &config)], &mut [])
+            .await
+    }
+
+    /// Enable an interrupt source and map it to an interrupt pin.
+    pub async fn enable_interrupt(
+        &mut self,
+        source: InterruptSource,
+        pin: InterruptPin,
+    ) -> Result<(), Error<CommE>> {
+        self.run(enable_interrupt_ops(source, pin), &mut []).await
+    }
+
+    /// Disable an interrupt source.
+    pub async fn disable_interrupt(&mut self, source: InterruptSource) -> Result<(), Error<CommE>> {
+        self.run([disable_interrupt_op(source)], &mut []).await
+    }
+
+    /// Run a register access sequence without delays, storing the data
+    /// read in `output`.
+    pub(crate) async fn run(
+        &mut self,
+        ops: impl IntoIterator<Item = Op>,
+        output: &mut [u8],
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(ops, &mut NoDelay, output).await
+    }
+
+    /// Run a register access sequence, storing the data read in `output`.
+    ///
+    /// See [`Bmi160::run_delayed()`](crate::Bmi160::run_delayed).
+    pub(crate) async fn run_delayed<D: DelayNs>(
+        &mut self,
+        ops: impl IntoIterator<Item = Op>,
+        delay: &mut D,
+        mut output: &mut [u8],
+    ) -> Result<(), Error<CommE>> {
+        for op in ops {
+            match op {
+                Op::Write(register, value) => self.iface.write_register(register, value).await?,
+                Op::Update {
+                    register,
+                    keep,
+                    bits,
+                } => {
+                    let value = self.iface.read_register(register).await?;
+                    self.iface
+                        .write_register(register, (value & keep) | bits)
+                        .await?;
+                }
+                Op::Read(register, len) => {
+                    let mut data = [0; MAX_READ_LEN + 1];
+                    data[0] = register;
+                    self.iface.read_data(&mut data[..=len]).await?;
+                    take_output(&mut output, len).copy_from_slice(&data[1..=len]);
+                }
+                Op::DelayUs(us) => delay.delay_us(us).await,
+                Op::WaitMagnet => self.wait_magnet_manual_op().await?,
+                Op::WriteMagnet(register, value) => {
+                    self.iface.write_register(Register::MAG_IF_4, value).await?;
+                    self.iface
+                        .write_register(Register::MAG_IF_3, register)
+                        .await?;
+                    self.wait_magnet_manual_op().await?;
+                }
+                Op::ReadMagnet(register) => {
+                    self.iface
+                        .write_register(Register::MAG_IF_2, register)
+                        .await?;
+                    self.wait_magnet_manual_op().await?;
+                    take_output(&mut output, 1)[0] =
+                        self.iface.read_register(Register::MAG).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn wait_magnet_manual_op(&mut self) -> Result<(), Error<CommE>> {
+        for _ in 0..MAGNET_OP_MAX_POLLS {
+            if (self.iface.read_register(Register::STATUS).await? & BitFlags::MAG_MAN_OP) == 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
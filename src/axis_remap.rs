@@ -0,0 +1,304 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    Bmi160, Data, Error, Sensor3DData, Sensor3DDataScaled,
+};
+
+/// Sensor axis with its direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum SignedAxis {
+    /// Sensor X axis
+    PosX,
+    /// Inverted sensor X axis
+    NegX,
+    /// Sensor Y axis
+    PosY,
+    /// Inverted sensor Y axis
+    NegY,
+    /// Sensor Z axis
+    PosZ,
+    /// Inverted sensor Z axis
+    NegZ,
+}
+
+impl SignedAxis {
+    fn index(self) -> usize {
+        match self {
+            SignedAxis::PosX | SignedAxis::NegX => 0,
+            SignedAxis::PosY | SignedAxis::NegY => 1,
+            SignedAxis::PosZ | SignedAxis::NegZ => 2,
+        }
+    }
+
+    fn is_negative(self) -> bool {
+        matches!(self, SignedAxis::NegX | SignedAxis::NegY | SignedAxis::NegZ)
+    }
+}
+
+/// Transformation from the sensor axes to the board axes
+///
+/// This is applied to the accelerometer and gyroscope data and to the
+/// scaled magnetometer data. The raw magnetometer data returned by
+/// [`data()`](crate::Bmi160::data) stays in the sensor axes because the trim
+/// compensation only applies to them. FIFO data is never remapped
+/// automatically.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum AxisRemap {
+    /// Right-angle rotation. Each board axis is given by a sensor axis.
+    ///
+    /// Only the 24 proper rotations are accepted.
+    Rotation {
+        /// Sensor axis along the board X axis
+        x: SignedAxis,
+        /// Sensor axis along the board Y axis
+        y: SignedAxis,
+        /// Sensor axis along the board Z axis
+        z: SignedAxis,
+    },
+    /// Arbitrary matrix multiplied with the sensor data, row by row.
+    ///
+    /// Only finite and invertible matrices are accepted.
+    /// Raw results are rounded and saturated to the `i16` range.
+    Matrix([[f32; 3]; 3]),
+}
+
+impl Default for AxisRemap {
+    fn default() -> Self {
+        AxisRemap::IDENTITY
+    }
+}
+
+impl AxisRemap {
+    /// Board axes equal to the sensor axes
+    pub const IDENTITY: AxisRemap = AxisRemap::Rotation {
+        x: SignedAxis::PosX,
+        y: SignedAxis::PosY,
+        z: SignedAxis::PosZ,
+    };
+
+    /// Whether this is a finite invertible matrix or one of the 24
+    /// right-angle rotations
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            AxisRemap::Rotation { x, y, z } => {
+                let (i, j, k) = (x.index(), y.index(), z.index());
+                if i == j || j == k || i == k {
+                    return false;
+                }
+                let even_permutation = (j + 3 - i) % 3 == 1;
+                let negations = [x, y, z].iter().filter(|a| a.is_negative()).count();
+                even_permutation == (negations % 2 == 0)
+            }
+            AxisRemap::Matrix(m) => {
+                let det = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                    - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                    + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+                m.iter().flatten().all(|v| v.is_finite()) && det.is_finite() && det != 0.0
+            }
+        }
+    }
+
+    /// Transform raw sensor data into the board axes.
+    pub fn remap(&self, data: Sensor3DData) -> Sensor3DData {
+        let v = [data.x, data.y, data.z];
+        let [x, y, z] = match self {
+            AxisRemap::Rotation { x, y, z } => {
+                let pick = |a: SignedAxis| {
+                    let value = v[a.index()];
+                    if a.is_negative() {
+                        value.saturating_neg()
+                    } else {
+                        value
+                    }
+                };
+                [pick(*x), pick(*y), pick(*z)]
+            }
+            AxisRemap::Matrix(m) => {
+                let v = v.map(f32::from);
+                // `as` saturates to the i16 range
+                m.map(|row| round(row[0] * v[0] + row[1] * v[1] + row[2] * v[2]) as i16)
+            }
+        };
+        Sensor3DData { x, y, z }
+    }
+
+    /// Transform scaled sensor data into the board axes.
+    pub fn remap_scaled(&self, data: Sensor3DDataScaled) -> Sensor3DDataScaled {
+        let v = [data.x, data.y, data.z];
+        let [x, y, z] = match self {
+            AxisRemap::Rotation { x, y, z } => {
+                let pick = |a: SignedAxis| {
+                    let value = v[a.index()];
+                    if a.is_negative() {
+                        -value
+                    } else {
+                        value
+                    }
+                };
+                [pick(*x), pick(*y), pick(*z)]
+            }
+            AxisRemap::Matrix(m) => m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2]),
+        };
+        Sensor3DDataScaled { x, y, z }
+    }
+
+    /// Transform the raw accelerometer and gyroscope data into the board
+    /// axes, e.g. for samples decoded with the [`fifo`](crate::fifo) module.
+    ///
+    /// The raw magnetometer data is left in the sensor axes because the
+    /// trim compensation only applies to them. It is transformed after the
+    /// compensation by [`data_scaled()`](crate::Bmi160::data_scaled).
+    pub fn remap_data(&self, data: Data) -> Data {
+        Data {
+            accel: data.accel.map(|d| self.remap(d)),
+            gyro: data.gyro.map(|d| self.remap(d)),
+            ..data
+        }
+    }
+}
+
+/// Error returned when setting a rotation that is not one of the 24
+/// right-angle rotations or a matrix that is not finite and invertible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct InvalidAxisRemap;
+
+/// Round half away from zero
+pub(crate) fn round(value: f32) -> f32 {
+    if value < 0.0 {
+        value - 0.5
+    } else {
+        value + 0.5
+    }
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Set the transformation from the sensor axes to the board axes.
+    ///
+    /// This is applied to the data returned by all data methods except for
+    /// the raw magnetometer data of [`data()`](Self::data). Use
+    /// [`AxisRemap::remap_data()`] or [`scale_data()`](Self::scale_data)
+    /// for FIFO samples.
+    ///
+    /// Returns `InvalidAxisRemap` if the rotation or matrix is not
+    /// [valid](AxisRemap::is_valid).
+    pub fn set_axis_remap(&mut self, remap: AxisRemap) -> Result<(), InvalidAxisRemap> {
+        if !remap.is_valid() {
+            return Err(InvalidAxisRemap);
+        }
+        self.axis_remap = remap;
+        Ok(())
+    }
+
+    /// Get the transformation from the sensor axes to the board axes.
+    pub fn axis_remap(&self) -> AxisRemap {
+        self.axis_remap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SignedAxis::*;
+
+    const DATA: Sensor3DData = Sensor3DData { x: 1, y: 2, z: 3 };
+
+    #[test]
+    fn there_are_24_rotations() {
+        let axes = [PosX, NegX, PosY, NegY, PosZ, NegZ];
+        let mut count = 0;
+        for x in axes {
+            for y in axes {
+                for z in axes {
+                    if (AxisRemap::Rotation { x, y, z }).is_valid() {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(count, 24);
+    }
+
+    #[test]
+    fn rejects_reflection() {
+        let remap = AxisRemap::Rotation {
+            x: PosY,
+            y: PosX,
+            z: PosZ,
+        };
+        assert!(!remap.is_valid());
+    }
+
+    #[test]
+    fn rejects_degenerate_matrices() {
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        assert!(AxisRemap::Matrix(identity).is_valid());
+        assert!(!AxisRemap::Matrix([[0.0; 3]; 3]).is_valid());
+        let mut singular = identity;
+        singular[2] = [1.0, 1.0, 0.0];
+        assert!(!AxisRemap::Matrix(singular).is_valid());
+        let mut nan = identity;
+        nan[1][2] = f32::NAN;
+        assert!(!AxisRemap::Matrix(nan).is_valid());
+        let mut infinite = identity;
+        infinite[0][0] = f32::INFINITY;
+        assert!(!AxisRemap::Matrix(infinite).is_valid());
+    }
+
+    #[test]
+    fn can_rotate_around_z() {
+        let remap = AxisRemap::Rotation {
+            x: PosY,
+            y: NegX,
+            z: PosZ,
+        };
+        assert!(remap.is_valid());
+        assert_eq!(remap.remap(DATA), Sensor3DData { x: 2, y: -1, z: 3 });
+    }
+
+    #[test]
+    fn rotation_saturates() {
+        let remap = AxisRemap::Rotation {
+            x: NegX,
+            y: NegY,
+            z: PosZ,
+        };
+        let data = Sensor3DData {
+            x: i16::MIN,
+            y: 1,
+            z: 0,
+        };
+        let expected = Sensor3DData {
+            x: i16::MAX,
+            y: -1,
+            z: 0,
+        };
+        assert_eq!(remap.remap(data), expected);
+    }
+
+    #[test]
+    fn can_apply_matrix() {
+        let remap = AxisRemap::Matrix([[0.5, 0.0, 0.0], [0.0, 0.0, -1.0], [1e6, 0.0, 0.0]]);
+        let expected = Sensor3DData {
+            x: 1,
+            y: -3,
+            z: i16::MAX,
+        };
+        assert_eq!(remap.remap(DATA), expected);
+        let scaled = Sensor3DDataScaled {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+        let expected = Sensor3DDataScaled {
+            x: 0.5,
+            y: -3.0,
+            z: 1e6,
+        };
+        assert_eq!(remap.remap_scaled(scaled), expected);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    Bmi160, Error, Sensor3DDataScaled,
+};
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const FORMAT_VERSION: u8 = 1;
+const SENSOR_LEN: usize = 12 * 4;
+
+/// Calibration of a single 3-axis sensor
+///
+/// The corrected data is `matrix * (data - bias)` in the scaled units of
+/// the sensor. The matrix combines the scale factors and the cross-axis
+/// (misalignment) terms. For the magnetometer, the bias is the hard-iron
+/// offset and the matrix the soft-iron correction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SensorCalibration {
+    /// Bias subtracted from the data
+    pub bias: [f32; 3],
+    /// Correction matrix multiplied with the unbiased data, row by row
+    pub matrix: [[f32; 3]; 3],
+}
+
+impl Default for SensorCalibration {
+    fn default() -> Self {
+        SensorCalibration::IDENTITY
+    }
+}
+
+impl SensorCalibration {
+    /// Calibration leaving the data unchanged
+    pub const IDENTITY: SensorCalibration = SensorCalibration {
+        bias: [0.0; 3],
+        matrix: IDENTITY,
+    };
+
+    /// Apply the calibration to scaled data.
+    pub fn apply(&self, data: Sensor3DDataScaled) -> Sensor3DDataScaled {
+        let v = [
+            data.x - self.bias[0],
+            data.y - self.bias[1],
+            data.z - self.bias[2],
+        ];
+        let [x, y, z] = self
+            .matrix
+            .map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2]);
+        Sensor3DDataScaled { x, y, z }
+    }
+
+    fn write_bytes(&self, bytes: &mut [u8]) {
+        let values = self.bias.iter().chain(self.matrix.iter().flatten());
+        for (chunk, value) in bytes.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut values = [0.0; 12];
+        for (value, chunk) in values.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        let row = |i: usize| [values[3 + 3 * i], values[4 + 3 * i], values[5 + 3 * i]];
+        SensorCalibration {
+            bias: [values[0], values[1], values[2]],
+            matrix: [row(0), row(1), row(2)],
+        }
+    }
+}
+
+/// Host-side calibration of the accelerometer, gyroscope and magnetometer
+///
+/// This is applied to the scaled data in the sensor axes, before the axis
+/// remapping. The accelerometer is calibrated in g, the gyroscope in °/s
+/// and the magnetometer in µT.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Calibration {
+    /// Accelerometer calibration
+    pub accel: SensorCalibration,
+    /// Gyroscope calibration
+    pub gyro: SensorCalibration,
+    /// Magnetometer calibration
+    pub magnet: SensorCalibration,
+}
+
+impl Calibration {
+    /// Calibration leaving the data unchanged
+    pub const IDENTITY: Calibration = Calibration {
+        accel: SensorCalibration::IDENTITY,
+        gyro: SensorCalibration::IDENTITY,
+        magnet: SensorCalibration::IDENTITY,
+    };
+
+    /// Length of the serialized calibration in bytes
+    pub const SERIALIZED_LEN: usize = 1 + 3 * SENSOR_LEN + 1;
+
+    /// Serialize the calibration.
+    ///
+    /// The format is a version byte, the accelerometer, gyroscope and
+    /// magnetometer bias and matrix as little-endian `f32` values and
+    /// a CRC-8 checksum.
+    pub fn to_bytes(&self) -> [u8; Self::SERIALIZED_LEN] {
+        let mut bytes = [0; Self::SERIALIZED_LEN];
+        bytes[0] = FORMAT_VERSION;
+        let sensors = [&self.accel, &self.gyro, &self.magnet];
+        for (chunk, sensor) in bytes[1..].chunks_exact_mut(SENSOR_LEN).zip(sensors) {
+            sensor.write_bytes(chunk);
+        }
+        bytes[Self::SERIALIZED_LEN - 1] = crc8(&bytes[..Self::SERIALIZED_LEN - 1]);
+        bytes
+    }
+
+    /// Deserialize a calibration written with [`to_bytes()`](Self::to_bytes).
+    ///
+    /// Returns `None` if the length, version or checksum is wrong.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (&checksum, data) = bytes.split_last()?;
+        if bytes.len() != Self::SERIALIZED_LEN
+            || data[0] != FORMAT_VERSION
+            || crc8(data) != checksum
+        {
+            return None;
+        }
+        let sensor = |i: usize| SensorCalibration::from_bytes(&data[1 + i * SENSOR_LEN..]);
+        Some(Calibration {
+            accel: sensor(0),
+            gyro: sensor(1),
+            magnet: sensor(2),
+        })
+    }
+}
+
+/// CRC-8 with polynomial 0x07
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Set the host-side calibration applied to the scaled data.
+    ///
+    /// This is applied by [`data_scaled()`](Self::data_scaled),
+    /// [`data_si()`](Self::data_si), [`scale_data()`](Self::scale_data) and
+    /// to the samples of the `InterruptStream` but not by
+    /// [`data()`](Self::data), [`data_milli()`](Self::data_milli) or the
+    /// frames decoded with the [`fifo`](crate::fifo) module.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// Get the host-side calibration.
+    pub fn calibration(&self) -> Calibration {
+        self.calibration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calibration() -> Calibration {
+        Calibration {
+            accel: SensorCalibration {
+                bias: [0.01, -0.02, 0.03],
+                matrix: [[1.01, 0.002, 0.0], [0.0, 0.99, -0.001], [0.0, 0.0, 1.0]],
+            },
+            gyro: SensorCalibration {
+                bias: [0.5, -0.25, 1.5],
+                matrix: IDENTITY,
+            },
+            magnet: SensorCalibration {
+                bias: [12.0, -30.5, 4.25],
+                matrix: [[1.1, 0.05, 0.0], [0.05, 0.9, 0.0], [0.0, 0.0, 1.0]],
+            },
+        }
+    }
+
+    #[test]
+    fn can_apply() {
+        let cal = SensorCalibration {
+            bias: [1.0, 2.0, 3.0],
+            matrix: [[2.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+        };
+        let data = Sensor3DDataScaled {
+            x: 2.0,
+            y: 4.0,
+            z: 6.0,
+        };
+        let expected = Sensor3DDataScaled {
+            x: 2.0,
+            y: 3.0,
+            z: -2.0,
+        };
+        assert_eq!(cal.apply(data), expected);
+    }
+
+    #[test]
+    fn identity_leaves_data_unchanged() {
+        let data = Sensor3DDataScaled {
+            x: 0.1,
+            y: -1e-3,
+            z: 123.456,
+        };
+        assert_eq!(SensorCalibration::IDENTITY.apply(data), data);
+    }
+
+    #[test]
+    fn can_serialize_and_deserialize() {
+        let bytes = calibration().to_bytes();
+        assert_eq!(bytes.len(), 146);
+        assert_eq!(bytes[0], FORMAT_VERSION);
+        assert_eq!(Calibration::from_bytes(&bytes), Some(calibration()));
+    }
+
+    #[test]
+    fn rejects_corrupted_data() {
+        let mut bytes = calibration().to_bytes();
+        bytes[20] ^= 0x10;
+        assert_eq!(Calibration::from_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn rejects_wrong_length_and_version() {
+        let bytes = calibration().to_bytes();
+        assert_eq!(Calibration::from_bytes(&bytes[..145]), None);
+        assert_eq!(Calibration::from_bytes(&[]), None);
+        let mut bytes = Calibration::IDENTITY.to_bytes();
+        bytes[0] = 2;
+        bytes[145] = crc8(&bytes[..145]);
+        assert_eq!(Calibration::from_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn crc8_check_value() {
+        assert_eq!(crc8(b"123456789"), 0xF4);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    Bmi160, Error, SensorSelector, SensorTime, SensorTimeExtender,
+};
+
+/// Host time converted from the sensor time
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct HostTime {
+    /// Host time in µs
+    pub micros: u64,
+    /// Estimated error in µs: the largest fit residual plus the average
+    /// half read duration
+    pub error_micros: f32,
+}
+
+/// Linear fit of the host time over the sensor time, relative to the newest
+/// pair
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+struct Fit {
+    sensor_ref: u64,
+    host_ref: u64,
+    /// Host time at the reference sensor time minus `host_ref` in µs
+    intercept: f64,
+    /// Host µs per sensor time tick
+    slope: f64,
+    error: f32,
+}
+
+/// Synchronization of the sensor time with a host clock
+///
+/// The host clock is sampled in µs right before and after reading the
+/// sensor time. The middle of each read is paired with the sensor time and
+/// the last `N` pairs are fitted with a line, estimating the offset and the
+/// drift between both clocks. Sensor time values read from the data
+/// registers or FIFO sensor time frames can then be converted into host
+/// time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct ClockSync<const N: usize> {
+    extender: SensorTimeExtender,
+    /// Extended sensor time, host time in µs and half read duration in µs
+    pairs: [(u64, u64, u32); N],
+    len: usize,
+    next: usize,
+    fit: Option<Fit>,
+}
+
+impl<const N: usize> Default for ClockSync<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> ClockSync<N> {
+    /// Create a new synchronizer.
+    pub fn new() -> Self {
+        ClockSync {
+            extender: SensorTimeExtender::new(),
+            pairs: [(0, 0, 0); N],
+            len: 0,
+            next: 0,
+            fit: None,
+        }
+    }
+
+    /// Add a sensor time read between the host times `host_before` and
+    /// `host_after` in µs.
+    ///
+    /// Pairs must be added in order, at least once per sensor time
+    /// wraparound period of 655.36 s.
+    pub fn add_pair(&mut self, host_before: u64, host_after: u64, time: SensorTime) {
+        if N == 0 {
+            return;
+        }
+        let (before, after) = (host_before.min(host_after), host_before.max(host_after));
+        let half_window = ((after - before) / 2).min(u64::from(u32::MAX)) as u32;
+        let sensor = self.extender.extend(time);
+        self.pairs[self.next] = (sensor, before + (after - before) / 2, half_window);
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        self.fit = Some(self.compute_fit());
+    }
+
+    /// Number of pairs in the fit
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no pair has been added
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Discard all pairs.
+    pub fn clear(&mut self) {
+        self.extender.reset();
+        self.len = 0;
+        self.next = 0;
+        self.fit = None;
+    }
+
+    /// Host clock drift relative to the sensor clock in ppm.
+    ///
+    /// Positive values mean that the host clock runs faster. Returns `None`
+    /// until two pairs have been added.
+    pub fn drift_ppm(&self) -> Option<f32> {
+        match self.fit {
+            Some(fit) if self.len >= 2 => Some(((fit.slope / NOMINAL_SLOPE - 1.0) * 1e6) as f32),
+            _ => None,
+        }
+    }
+
+    /// Convert a sensor time into host time.
+    ///
+    /// The sensor time must be within 327.68 s of the newest pair.
+    /// Returns `None` if no pair has been added.
+    pub fn to_host(&self, time: SensorTime) -> Option<HostTime> {
+        let fit = self.fit?;
+        let shift = 32 - 24;
+        let delta =
+            ((time.wrapping_sub(SensorTime(fit.sensor_ref as u32)) << shift) as i32) >> shift;
+        let host = fit.host_ref as f64 + fit.intercept + fit.slope * f64::from(delta);
+        Some(HostTime {
+            micros: if host > 0.0 { (host + 0.5) as u64 } else { 0 },
+            error_micros: fit.error,
+        })
+    }
+
+    fn compute_fit(&self) -> Fit {
+        let newest = self.pairs[(self.next + N - 1) % N];
+        let (sensor_ref, host_ref) = (newest.0, newest.1);
+        let pairs = &self.pairs[..self.len];
+        let n = self.len as f64;
+        let points = pairs.iter().map(|&(s, h, _)| {
+            let x = s as f64 - sensor_ref as f64;
+            let y = (h as i64 - host_ref as i64) as f64;
+            (x, y)
+        });
+        let (sum_x, sum_y) = points
+            .clone()
+            .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let (mean_x, mean_y) = (sum_x / n, sum_y / n);
+        let (sxx, sxy) = points.clone().fold((0.0, 0.0), |(sxx, sxy), (x, y)| {
+            (
+                sxx + (x - mean_x) * (x - mean_x),
+                sxy + (x - mean_x) * (y - mean_y),
+            )
+        });
+        let slope = if sxx > 0.0 { sxy / sxx } else { NOMINAL_SLOPE };
+        let intercept = mean_y - slope * mean_x;
+        let residual = points
+            .map(|(x, y)| {
+                let r = y - intercept - slope * x;
+                if r < 0.0 {
+                    -r
+                } else {
+                    r
+                }
+            })
+            .fold(0.0, f64::max);
+        let half_window = pairs.iter().map(|p| f64::from(p.2)).sum::<f64>() / n;
+        Fit {
+            sensor_ref,
+            host_ref,
+            intercept,
+            slope,
+            error: (residual + half_window) as f32,
+        }
+    }
+}
+
+/// Nominal µs per sensor time tick
+const NOMINAL_SLOPE: f64 = 39.0625;
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Read the sensor time and add it to the synchronizer paired with the
+    /// host time in µs returned by `host_micros` before and after the read.
+    ///
+    /// Returns the sensor time read.
+    pub fn sync_clock<const N: usize, F: FnMut() -> u64>(
+        &mut self,
+        sync: &mut ClockSync<N>,
+        mut host_micros: F,
+    ) -> Result<SensorTime, Error<CommE>> {
+        let before = host_micros();
+        let data = self.sensor_data(SensorSelector::new().time())?;
+        let after = host_micros();
+        let time = SensorTime(data.time.unwrap_or_default());
+        sync.add_pair(before, after, time);
+        Ok(time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Host clock running 100 ppm faster with an offset of 1 s
+    fn host(ticks: u64) -> u64 {
+        1_000_000 + ticks * 3_906_640_625 / 100_000_000
+    }
+
+    #[test]
+    fn cannot_convert_without_pairs() {
+        let sync = ClockSync::<4>::new();
+        assert_eq!(sync.to_host(SensorTime(0)), None);
+        assert_eq!(sync.drift_ppm(), None);
+    }
+
+    #[test]
+    fn single_pair_uses_nominal_rate() {
+        let mut sync = ClockSync::<4>::new();
+        sync.add_pair(990, 1010, SensorTime(1000));
+        let time = sync.to_host(SensorTime(1256)).unwrap();
+        assert_eq!(time.micros, 1000 + 10_000);
+        assert_eq!(time.error_micros, 10.0);
+        assert_eq!(sync.drift_ppm(), None);
+    }
+
+    #[test]
+    fn estimates_offset_and_drift() {
+        let mut sync = ClockSync::<4>::new();
+        for i in 0..6 {
+            let ticks = 0xFF_0000 + i * 25_600;
+            sync.add_pair(host(ticks) - 2, host(ticks) + 2, SensorTime(ticks as u32));
+        }
+        assert_eq!(sync.len(), 4);
+        let drift = sync.drift_ppm().unwrap();
+        assert!((drift - 100.0).abs() < 0.1, "{}", drift);
+        // FIFO frames before the newest pair, across the wraparound
+        for ticks in [0xFF_8000, 0x100_0100, 0x101_0000] {
+            let time = sync.to_host(SensorTime(ticks as u32)).unwrap();
+            assert!(time.micros.abs_diff(host(ticks)) <= 1);
+            assert!(time.error_micros >= 2.0 && time.error_micros < 3.0);
+        }
+    }
+
+    #[test]
+    fn can_clear() {
+        let mut sync = ClockSync::<2>::new();
+        sync.add_pair(0, 10, SensorTime(5));
+        sync.clear();
+        assert!(sync.is_empty());
+        assert_eq!(sync.to_host(SensorTime(5)), None);
+    }
+}
@@ -0,0 +1,408 @@
+use crate::{
+    device_impl::{
+        accel_power_mode_cmd, decode_power_mode, gyro_power_mode_cmd, magnet_power_mode_cmd,
+    },
+    fifo_impl::fifo_config_1,
+    interface::{ReadData, WriteData},
+    interrupt_impl::{int_en_1_flag, int_map_1_with, int_out_ctrl_with_config},
+    sequence::Op,
+    AccelerometerBandwidth, AccelerometerConfig, AccelerometerOdr, AccelerometerPowerMode,
+    AccelerometerRange, BitFlags, Bmi160, Config, Error, FifoConfig, GyroscopeBandwidth,
+    GyroscopeConfig, GyroscopeOdr, GyroscopePowerMode, GyroscopeRange, InterruptConfig,
+    InterruptPin, InterruptPinConfig, InterruptSource, MagnetometerPowerMode, Offsets, Register,
+    SensorPowerMode,
+};
+use embedded_hal::delay::DelayNs;
+
+/// Minimum delay after a register write in suspend or low power mode in µs
+pub(crate) const WRITE_DELAY_US: u32 = 450;
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Write the whole device configuration.
+    ///
+    /// The configuration registers are written first, waiting after each
+    /// write as required in suspend mode. Then the power modes are set,
+    /// waiting until each sensor has started.
+    ///
+    /// Returns `Error::InvalidInputData` if an accelerometer averaging
+    /// exponent is above 7, the accelerometer low power mode is selected
+    /// without an averaging bandwidth or a gyroscope offset does not fit in
+    /// 10 bits.
+    pub fn configure<D: DelayNs>(
+        &mut self,
+        config: &Config,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(configure_ops(config)?, delay, &mut [])?;
+        self.accel_range = config.accel_range;
+        self.gyro_range = config.gyro_range;
+        Ok(())
+    }
+
+    /// Read the whole device configuration.
+    ///
+    /// The ranges read are used to scale the data from now on.
+    ///
+    /// Returns `Error::InvalidDeviceData` if a register contains a reserved value.
+    pub fn read_config(&mut self) -> Result<Config, Error<CommE>> {
+        let mut data = [0; CONFIG_READ_LEN];
+        self.run(CONFIG_READS, &mut data)?;
+        let config = ConfigRegisters::from_reads(&data).decode()?;
+        self.accel_range = config.accel_range;
+        self.gyro_range = config.gyro_range;
+        Ok(config)
+    }
+}
+
+/// Register reads done by `read_config()` in order
+pub(crate) const CONFIG_READS: [Op; 7] = [
+    Op::Read(Register::PMU_STATUS, 1),
+    Op::Read(Register::INT_EN_1, 1),
+    Op::Read(Register::INT_OUT_CTRL, 1),
+    Op::Read(Register::INT_MAP_1, 1),
+    Op::Read(Register::ACC_CONF, 4),
+    Op::Read(Register::FIFO_CONFIG_0, 2),
+    Op::Read(Register::OFFSET_0, 7),
+];
+/// Number of bytes read by `CONFIG_READS`
+pub(crate) const CONFIG_READ_LEN: usize = 17;
+
+/// Raw configuration registers
+#[derive(Debug, Default)]
+pub(crate) struct ConfigRegisters {
+    pub(crate) pmu_status: u8,
+    pub(crate) conf: [u8; 4],
+    pub(crate) fifo: [u8; 2],
+    pub(crate) int_en_1: u8,
+    pub(crate) int_out_ctrl: u8,
+    pub(crate) int_map_1: u8,
+    pub(crate) offsets: [u8; 7],
+}
+
+impl ConfigRegisters {
+    /// Split the data read with `CONFIG_READS`.
+    pub(crate) fn from_reads(data: &[u8; CONFIG_READ_LEN]) -> Self {
+        let mut registers = ConfigRegisters {
+            pmu_status: data[0],
+            int_en_1: data[1],
+            int_out_ctrl: data[2],
+            int_map_1: data[3],
+            ..ConfigRegisters::default()
+        };
+        registers.conf.copy_from_slice(&data[4..8]);
+        registers.fifo.copy_from_slice(&data[8..10]);
+        registers.offsets.copy_from_slice(&data[10..]);
+        registers
+    }
+
+    pub(crate) fn decode<E>(&self) -> Result<Config, Error<E>> {
+        let [acc_conf, acc_range, gyr_conf, gyr_range] = self.conf;
+        let fifo_config_1 = self.fifo[1];
+        let pin = |source| {
+            if (self.int_en_1 & int_en_1_flag(source)) == 0 {
+                None
+            } else if int_map_1_with(0, source, InterruptPin::Int1) & self.int_map_1 != 0 {
+                Some(InterruptPin::Int1)
+            } else if int_map_1_with(0, source, InterruptPin::Int2) & self.int_map_1 != 0 {
+                Some(InterruptPin::Int2)
+            } else {
+                None
+            }
+        };
+        Ok(Config {
+            power_mode: decode_power_mode(self.pmu_status),
+            accel_range: decode_accel_range(acc_range)?,
+            gyro_range: decode_gyro_range(gyr_range)?,
+            accel: decode_accel_config(acc_conf)?,
+            gyro: decode_gyro_config(gyr_conf)?,
+            fifo: FifoConfig {
+                accel: (fifo_config_1 & BitFlags::FIFO_ACC_EN) != 0,
+                gyro: (fifo_config_1 & BitFlags::FIFO_GYR_EN) != 0,
+                magnet: (fifo_config_1 & BitFlags::FIFO_MAG_EN) != 0,
+                time: (fifo_config_1 & BitFlags::FIFO_TIME_EN) != 0,
+                watermark: self.fifo[0],
+            },
+            interrupts: InterruptConfig {
+                int1: decode_pin_config(self.int_out_ctrl),
+                int2: decode_pin_config(self.int_out_ctrl >> 4),
+                data_ready: pin(InterruptSource::DataReady),
+                fifo_watermark: pin(InterruptSource::FifoWatermark),
+                fifo_full: pin(InterruptSource::FifoFull),
+            },
+            offsets: decode_offsets(&self.offsets),
+        })
+    }
+}
+
+/// Register writes done by `configure()` in order
+pub(crate) fn config_writes<E>(config: &Config) -> Result<[(u8, u8); 16], Error<E>> {
+    // The accelerometer only runs undersampled in low power mode.
+    if config.power_mode.accel == AccelerometerPowerMode::LowPower
+        && !matches!(config.accel.bandwidth, AccelerometerBandwidth::Averaging(_))
+    {
+        return Err(Error::InvalidInputData);
+    }
+    let offsets = encode_offsets(&config.offsets)?;
+    let interrupts = &config.interrupts;
+    let mut int_map_1 = 0;
+    let mut int_en_1 = 0;
+    for (source, pin) in [
+        (InterruptSource::DataReady, interrupts.data_ready),
+        (InterruptSource::FifoWatermark, interrupts.fifo_watermark),
+        (InterruptSource::FifoFull, interrupts.fifo_full),
+    ] {
+        if let Some(pin) = pin {
+            int_map_1 = int_map_1_with(int_map_1, source, pin);
+            int_en_1 |= int_en_1_flag(source);
+        }
+    }
+    let int_out_ctrl = int_out_ctrl_with_config(
+        int_out_ctrl_with_config(0, InterruptPin::Int1, &interrupts.int1),
+        InterruptPin::Int2,
+        &interrupts.int2,
+    );
+    Ok([
+        (Register::ACC_CONF, encode_accel_config(&config.accel)?),
+        (Register::ACC_RANGE, config.accel_range as u8),
+        (Register::GYR_CONF, encode_gyro_config(&config.gyro)),
+        (Register::GYR_RANGE, config.gyro_range as u8),
+        (Register::FIFO_CONFIG_0, config.fifo.watermark),
+        (Register::FIFO_CONFIG_1, fifo_config_1(&config.fifo)),
+        (Register::INT_OUT_CTRL, int_out_ctrl),
+        (Register::INT_MAP_1, int_map_1),
+        (Register::INT_EN_1, int_en_1),
+        (Register::OFFSET_0, offsets[0]),
+        (Register::OFFSET_0 + 1, offsets[1]),
+        (Register::OFFSET_0 + 2, offsets[2]),
+        (Register::OFFSET_0 + 3, offsets[3]),
+        (Register::OFFSET_0 + 4, offsets[4]),
+        (Register::OFFSET_0 + 5, offsets[5]),
+        (Register::OFFSET_6, offsets[6]),
+    ])
+}
+
+/// Register writes and delays done by `configure()` in order
+pub(crate) fn configure_ops<E>(config: &Config) -> Result<impl Iterator<Item = Op>, Error<E>> {
+    let writes = config_writes(config)?
+        .into_iter()
+        .flat_map(|(register, value)| [Op::Write(register, value), Op::DelayUs(WRITE_DELAY_US)]);
+    let power_modes = power_mode_steps(&config.power_mode)
+        .into_iter()
+        .flat_map(|(cmd, delay_us)| [Op::Write(Register::CMD, cmd), Op::DelayUs(delay_us)]);
+    Ok(writes.chain(power_modes))
+}
+
+/// Power mode commands and the time to wait after each of them in µs
+pub(crate) fn power_mode_steps(mode: &SensorPowerMode) -> [(u8, u32); 3] {
+    let accel_delay = match mode.accel {
+        AccelerometerPowerMode::Suspend => WRITE_DELAY_US,
+        AccelerometerPowerMode::Normal | AccelerometerPowerMode::LowPower => 3800,
+    };
+    let gyro_delay = match mode.gyro {
+        GyroscopePowerMode::Suspend => WRITE_DELAY_US,
+        GyroscopePowerMode::Normal | GyroscopePowerMode::FastStartUp => 80_000,
+    };
+    let magnet_delay = match mode.magnet {
+        MagnetometerPowerMode::Suspend => WRITE_DELAY_US,
+        MagnetometerPowerMode::Normal | MagnetometerPowerMode::LowPower => 650,
+    };
+    [
+        (accel_power_mode_cmd(mode.accel), accel_delay),
+        (gyro_power_mode_cmd(mode.gyro), gyro_delay),
+        (magnet_power_mode_cmd(mode.magnet), magnet_delay),
+    ]
+}
+
+fn encode_accel_config<E>(config: &AccelerometerConfig) -> Result<u8, Error<E>> {
+    let bandwidth = match config.bandwidth {
+        AccelerometerBandwidth::Osr4 => 0,
+        AccelerometerBandwidth::Osr2 => 1,
+        AccelerometerBandwidth::Normal => 2,
+        AccelerometerBandwidth::Averaging(n) if n <= 7 => BitFlags::ACC_US >> 4 | n,
+        AccelerometerBandwidth::Averaging(_) => return Err(Error::InvalidInputData),
+    };
+    Ok(bandwidth << 4 | config.odr as u8)
+}
+
+fn encode_gyro_config(config: &GyroscopeConfig) -> u8 {
+    (config.bandwidth as u8) << 4 | config.odr as u8
+}
+
+pub(crate) fn encode_offsets<E>(offsets: &Offsets) -> Result<[u8; 7], Error<E>> {
+    if offsets.gyro.iter().any(|o| !(-512..=511).contains(o)) {
+        return Err(Error::InvalidInputData);
+    }
+    let gyro = offsets.gyro.map(|o| o as u16 & 0x3FF);
+    let mut offset_6 = (gyro[0] >> 8 | (gyro[1] >> 8) << 2 | (gyro[2] >> 8) << 4) as u8;
+    if offsets.gyro_enabled {
+        offset_6 |= BitFlags::GYR_OFF_EN;
+    }
+    if offsets.accel_enabled {
+        offset_6 |= BitFlags::ACC_OFF_EN;
+    }
+    Ok([
+        offsets.accel[0] as u8,
+        offsets.accel[1] as u8,
+        offsets.accel[2] as u8,
+        gyro[0] as u8,
+        gyro[1] as u8,
+        gyro[2] as u8,
+        offset_6,
+    ])
+}
+
+pub(crate) fn decode_offsets(data: &[u8]) -> Offsets {
+    let offset_6 = data[6];
+    let gyro = |i: usize| {
+        let value = u16::from(data[3 + i]) | u16::from((offset_6 >> (2 * i)) & 0b11) << 8;
+        ((value << 6) as i16) >> 6
+    };
+    Offsets {
+        accel_enabled: (offset_6 & BitFlags::ACC_OFF_EN) != 0,
+        accel: [data[0] as i8, data[1] as i8, data[2] as i8],
+        gyro_enabled: (offset_6 & BitFlags::GYR_OFF_EN) != 0,
+        gyro: [gyro(0), gyro(1), gyro(2)],
+    }
+}
+
+fn decode_pin_config(value: u8) -> InterruptPinConfig {
+    InterruptPinConfig {
+        output_enabled: (value & BitFlags::INT1_OUTPUT_EN) != 0,
+        active_high: (value & BitFlags::INT1_LVL) != 0,
+        open_drain: (value & BitFlags::INT1_OD) != 0,
+        edge_triggered: (value & BitFlags::INT1_EDGE_CTRL) != 0,
+    }
+}
+
+pub(crate) fn decode_accel_range<E>(value: u8) -> Result<AccelerometerRange, Error<E>> {
+    match value & 0b1111 {
+        0b0011 => Ok(AccelerometerRange::G2),
+        0b0101 => Ok(AccelerometerRange::G4),
+        0b1000 => Ok(AccelerometerRange::G8),
+        _ => Err(Error::InvalidDeviceData),
+    }
+}
+
+pub(crate) fn decode_gyro_range<E>(value: u8) -> Result<GyroscopeRange, Error<E>> {
+    match value & 0b111 {
+        0 => Ok(GyroscopeRange::Scale2000),
+        1 => Ok(GyroscopeRange::Scale1000),
+        2 => Ok(GyroscopeRange::Scale500),
+        3 => Ok(GyroscopeRange::Scale250),
+        4 => Ok(GyroscopeRange::Scale125),
+        _ => Err(Error::InvalidDeviceData),
+    }
+}
+
+pub(crate) fn decode_accel_config<E>(value: u8) -> Result<AccelerometerConfig, Error<E>> {
+    let odr = match value & 0b1111 {
+        1 => AccelerometerOdr::Hz0_78,
+        2 => AccelerometerOdr::Hz1_56,
+        3 => AccelerometerOdr::Hz3_12,
+        4 => AccelerometerOdr::Hz6_25,
+        5 => AccelerometerOdr::Hz12_5,
+        6 => AccelerometerOdr::Hz25,
+        7 => AccelerometerOdr::Hz50,
+        8 => AccelerometerOdr::Hz100,
+        9 => AccelerometerOdr::Hz200,
+        10 => AccelerometerOdr::Hz400,
+        11 => AccelerometerOdr::Hz800,
+        12 => AccelerometerOdr::Hz1600,
+        _ => return Err(Error::InvalidDeviceData),
+    };
+    let bwp = (value >> 4) & 0b111;
+    let bandwidth = if (value & BitFlags::ACC_US) != 0 {
+        AccelerometerBandwidth::Averaging(bwp)
+    } else {
+        match bwp {
+            0 => AccelerometerBandwidth::Osr4,
+            1 => AccelerometerBandwidth::Osr2,
+            2 => AccelerometerBandwidth::Normal,
+            _ => return Err(Error::InvalidDeviceData),
+        }
+    };
+    Ok(AccelerometerConfig { odr, bandwidth })
+}
+
+pub(crate) fn decode_gyro_config<E>(value: u8) -> Result<GyroscopeConfig, Error<E>> {
+    let odr = match value & 0b1111 {
+        6 => GyroscopeOdr::Hz25,
+        7 => GyroscopeOdr::Hz50,
+        8 => GyroscopeOdr::Hz100,
+        9 => GyroscopeOdr::Hz200,
+        10 => GyroscopeOdr::Hz400,
+        11 => GyroscopeOdr::Hz800,
+        12 => GyroscopeOdr::Hz1600,
+        13 => GyroscopeOdr::Hz3200,
+        _ => return Err(Error::InvalidDeviceData),
+    };
+    let bandwidth = match (value >> 4) & 0b11 {
+        0 => GyroscopeBandwidth::Osr4,
+        1 => GyroscopeBandwidth::Osr2,
+        2 => GyroscopeBandwidth::Normal,
+        _ => return Err(Error::InvalidDeviceData),
+    };
+    Ok(GyroscopeConfig { odr, bandwidth })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_roundtrip() {
+        let offsets = Offsets {
+            accel_enabled: true,
+            accel: [-128, 0, 127],
+            gyro_enabled: true,
+            gyro: [-512, 1, 511],
+        };
+        let encoded = encode_offsets::<()>(&offsets).unwrap();
+        assert_eq!(encoded, [0x80, 0, 0x7F, 0x00, 0x01, 0xFF, 0b1101_0010]);
+        assert_eq!(decode_offsets(&encoded), offsets);
+    }
+
+    #[test]
+    fn default_config_roundtrip() {
+        let config = Config::default();
+        let writes = config_writes::<()>(&config).unwrap();
+        let value = |register| writes.iter().find(|w| w.0 == register).unwrap().1;
+        let mut registers = ConfigRegisters {
+            int_en_1: value(Register::INT_EN_1),
+            int_out_ctrl: value(Register::INT_OUT_CTRL),
+            int_map_1: value(Register::INT_MAP_1),
+            ..ConfigRegisters::default()
+        };
+        for i in 0..4 {
+            registers.conf[i] = value(Register::ACC_CONF + i as u8);
+        }
+        registers.fifo[0] = value(Register::FIFO_CONFIG_0);
+        registers.fifo[1] = value(Register::FIFO_CONFIG_1);
+        for i in 0..7 {
+            registers.offsets[i] = value(Register::OFFSET_0 + i as u8);
+        }
+        assert_eq!(registers.conf, [0x28, 0x03, 0x28, 0x00]);
+        assert_eq!(registers.fifo, [0x04, 0x10]);
+        assert_eq!(registers.decode::<()>().unwrap(), config);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let mut config = Config::default();
+        config.accel.bandwidth = AccelerometerBandwidth::Averaging(8);
+        assert!(config_writes::<()>(&config).is_err());
+        let mut config = Config::default();
+        config.offsets.gyro[2] = 512;
+        assert!(config_writes::<()>(&config).is_err());
+        let mut config = Config::default();
+        config.power_mode.accel = AccelerometerPowerMode::LowPower;
+        assert!(config_writes::<()>(&config).is_err());
+        config.accel.bandwidth = AccelerometerBandwidth::Averaging(2);
+        assert!(config_writes::<()>(&config).is_ok());
+        assert!(decode_accel_config::<()>(0x20).is_err());
+        assert!(decode_gyro_config::<()>(0x25).is_err());
+    }
+}
@@ -1,9 +1,15 @@
 use crate::{
     interface::{I2cInterface, ReadData, SpiInterface, WriteData},
+    sequence::{take_output, NoDelay, Op, MAGNET_OP_MAX_POLLS, MAX_READ_LEN},
     types::{AccelerometerRange, GyroscopeRange},
-    AccelerometerPowerMode, BitFlags, Bmi160, Error, GyroscopePowerMode, MagnetometerPowerMode,
-    Register, SensorPowerMode, SlaveAddr, Status,
+    AccelerometerPowerMode, AxisRemap, BitFlags, Bmi160, Calibration, Error, GyroscopePowerMode,
+    I2cWatchdog, InterfaceConfig, MagnetometerPowerMode, Register, SensorPowerMode, SlaveAddr,
+    Status,
 };
+use embedded_hal::{delay::DelayNs, i2c::I2c, spi::SpiDevice};
+
+/// Chip ID of the BMI160
+pub(crate) const CHIP_ID: u8 = 0xD1;
 
 impl<I2C> Bmi160<I2cInterface<I2C>> {
     /// Create new instance of the BMI160 device communicating through I2C.
@@ -15,6 +21,9 @@ impl<I2C> Bmi160<I2cInterface<I2C>> {
             },
             accel_range: AccelerometerRange::default(),
             gyro_range: GyroscopeRange::default(),
+            magnet_conversion: None,
+            axis_remap: AxisRemap::IDENTITY,
+            calibration: Calibration::IDENTITY,
         }
     }
 
@@ -26,11 +35,17 @@ impl<I2C> Bmi160<I2cInterface<I2C>> {
 
 impl<SPI> Bmi160<SpiInterface<SPI>> {
     /// Create new instance of the BMI160 device communicating through SPI.
+    ///
+    /// The device starts in I2C mode after power-up. Call
+    /// [`init()`](Self::init) before any other method to switch it to SPI.
     pub fn new_with_spi(spi: SPI) -> Self {
         Bmi160 {
             iface: SpiInterface { spi },
             accel_range: AccelerometerRange::default(),
             gyro_range: GyroscopeRange::default(),
+            magnet_conversion: None,
+            axis_remap: AxisRemap::IDENTITY,
+            calibration: Calibration::IDENTITY,
         }
     }
 
@@ -40,6 +55,35 @@ impl<SPI> Bmi160<SpiInterface<SPI>> {
     }
 }
 
+impl<I2C, E> Bmi160<I2cInterface<I2C>>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Verify the chip ID.
+    ///
+    /// Returns `Error::InvalidChipId` if the device is not a BMI160.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        check_chip_id(self.chip_id()?)
+    }
+}
+
+impl<SPI, E> Bmi160<SpiInterface<SPI>>
+where
+    SPI: SpiDevice<u8, Error = E>,
+{
+    /// Switch the device to SPI mode and verify the chip ID.
+    ///
+    /// The device starts in I2C mode after power-up and switches to SPI
+    /// on the first rising edge of CS. This is done through a dummy read.
+    /// Call this before any other method.
+    ///
+    /// Returns `Error::InvalidChipId` if the device is not a BMI160.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        self.iface.read_register(Register::SPI_COMM_TEST)?;
+        check_chip_id(self.chip_id()?)
+    }
+}
+
 impl<DI, CommE> Bmi160<DI>
 where
     DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
@@ -52,40 +96,13 @@ where
     /// Get sensor power mode
     pub fn power_mode(&mut self) -> Result<SensorPowerMode, Error<CommE>> {
         let status = self.iface.read_register(Register::PMU_STATUS)?;
-        let accel = match status & (0b11 << 4) {
-            0 => AccelerometerPowerMode::Suspend,
-            0b10_0000 => AccelerometerPowerMode::LowPower,
-            _ => AccelerometerPowerMode::Normal,
-        };
-        let magnet = match status & 0b11 {
-            0 => MagnetometerPowerMode::Suspend,
-            2 => MagnetometerPowerMode::LowPower,
-            _ => MagnetometerPowerMode::Normal,
-        };
-        let gyro = match status & (0b11 << 2) {
-            0 => GyroscopePowerMode::Suspend,
-            0b1100 => GyroscopePowerMode::FastStartUp,
-            _ => GyroscopePowerMode::Normal,
-        };
-        Ok(SensorPowerMode {
-            accel,
-            gyro,
-            magnet,
-        })
+        Ok(decode_power_mode(status))
     }
 
     /// Get sensor status
     pub fn status(&mut self) -> Result<Status, Error<CommE>> {
         let status = self.iface.read_register(Register::STATUS)?;
-        Ok(Status {
-            accel_data_ready: (status & BitFlags::DRDY_ACC) != 0,
-            gyro_data_ready: (status & BitFlags::DRDY_GYR) != 0,
-            magnet_data_ready: (status & BitFlags::DRDY_MAG) != 0,
-            nvm_ready: (status & BitFlags::NVM_RDY) != 0,
-            foc_ready: (status & BitFlags::FOC_RDY) != 0,
-            magnet_manual_op: (status & BitFlags::MAG_MAN_OP) != 0,
-            gyro_self_test_ok: (status & BitFlags::GYR_SELF_TEST_OK) != 0,
-        })
+        Ok(decode_status(status))
     }
 
     /// Configure accelerometer power mode
@@ -93,22 +110,14 @@ where
         &mut self,
         mode: AccelerometerPowerMode,
     ) -> Result<(), Error<CommE>> {
-        let cmd = match mode {
-            AccelerometerPowerMode::Suspend => 0b0001_0000,
-            AccelerometerPowerMode::Normal => 0b0001_0001,
-            AccelerometerPowerMode::LowPower => 0b0001_0010,
-        };
-        self.iface.write_register(Register::CMD, cmd)
+        self.iface
+            .write_register(Register::CMD, accel_power_mode_cmd(mode))
     }
 
     /// Configure gyroscope power mode
     pub fn set_gyro_power_mode(&mut self, mode: GyroscopePowerMode) -> Result<(), Error<CommE>> {
-        let cmd = match mode {
-            GyroscopePowerMode::Suspend => 0b0001_0100,
-            GyroscopePowerMode::Normal => 0b0001_0101,
-            GyroscopePowerMode::FastStartUp => 0b0001_0111,
-        };
-        self.iface.write_register(Register::CMD, cmd)
+        self.iface
+            .write_register(Register::CMD, gyro_power_mode_cmd(mode))
     }
 
     /// Configure magnetometer power mode
@@ -116,12 +125,8 @@ where
         &mut self,
         mode: MagnetometerPowerMode,
     ) -> Result<(), Error<CommE>> {
-        let cmd = match mode {
-            MagnetometerPowerMode::Suspend => 0b0001_1000,
-            MagnetometerPowerMode::Normal => 0b0001_1001,
-            MagnetometerPowerMode::LowPower => 0b0001_1010,
-        };
-        self.iface.write_register(Register::CMD, cmd)
+        self.iface
+            .write_register(Register::CMD, magnet_power_mode_cmd(mode))
     }
 
     /// Set the accelerometer range
@@ -139,4 +144,184 @@ where
         self.gyro_range = range;
         Ok(())
     }
+
+    /// Get the primary interface configuration
+    pub fn interface_config(&mut self) -> Result<InterfaceConfig, Error<CommE>> {
+        let if_conf = self.iface.read_register(Register::IF_CONF)?;
+        let nv_conf = self.iface.read_register(Register::NV_CONF)?;
+        Ok(decode_interface_config(if_conf, nv_conf))
+    }
+
+    /// Configure the primary interface: 3-wire SPI and I2C watchdog.
+    ///
+    /// The secondary interface mode is kept.
+    pub fn set_interface_config(&mut self, config: InterfaceConfig) -> Result<(), Error<CommE>> {
+        self.run(interface_config_ops(&config), &mut [])
+    }
+
+    /// Run a register access sequence without delays, storing the data
+    /// read in `output`.
+    pub(crate) fn run(
+        &mut self,
+        ops: impl IntoIterator<Item = Op>,
+        output: &mut [u8],
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(ops, &mut NoDelay, output)
+    }
+
+    /// Run a register access sequence, storing the data read in `output`.
+    pub(crate) fn run_delayed<D: DelayNs>(
+        &mut self,
+        ops: impl IntoIterator<Item = Op>,
+        delay: &mut D,
+        mut output: &mut [u8],
+    ) -> Result<(), Error<CommE>> {
+        for op in ops {
+            match op {
+                Op::Write(register, value) => self.iface.write_register(register, value)?,
+                Op::Update {
+                    register,
+                    keep,
+                    bits,
+                } => {
+                    let value = self.iface.read_register(register)?;
+                    self.iface.write_register(register, (value & keep) | bits)?;
+                }
+                Op::Read(register, len) => {
+                    let mut data = [0; MAX_READ_LEN + 1];
+                    data[0] = register;
+                    self.iface.read_data(&mut data[..=len])?;
+                    take_output(&mut output, len).copy_from_slice(&data[1..=len]);
+                }
+                Op::DelayUs(us) => delay.delay_us(us),
+                Op::WaitMagnet => self.wait_magnet_manual_op()?,
+                Op::WriteMagnet(register, value) => {
+                    self.iface.write_register(Register::MAG_IF_4, value)?;
+                    self.iface.write_register(Register::MAG_IF_3, register)?;
+                    self.wait_magnet_manual_op()?;
+                }
+                Op::ReadMagnet(register) => {
+                    self.iface.write_register(Register::MAG_IF_2, register)?;
+                    self.wait_magnet_manual_op()?;
+                    take_output(&mut output, 1)[0] = self.iface.read_register(Register::MAG)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn wait_magnet_manual_op(&mut self) -> Result<(), Error<CommE>> {
+        for _ in 0..MAGNET_OP_MAX_POLLS {
+            if (self.iface.read_register(Register::STATUS)? & BitFlags::MAG_MAN_OP) == 0 {
+                return Ok(());
+            }
+        }
+        Err(Error::Timeout)
+    }
+}
+
+pub(crate) fn decode_power_mode(status: u8) -> SensorPowerMode {
+    let accel = match status & (0b11 << 4) {
+        0 => AccelerometerPowerMode::Suspend,
+        0b10_0000 => AccelerometerPowerMode::LowPower,
+        _ => AccelerometerPowerMode::Normal,
+    };
+    let magnet = match status & 0b11 {
+        0 => MagnetometerPowerMode::Suspend,
+        2 => MagnetometerPowerMode::LowPower,
+        _ => MagnetometerPowerMode::Normal,
+    };
+    let gyro = match status & (0b11 << 2) {
+        0 => GyroscopePowerMode::Suspend,
+        0b1100 => GyroscopePowerMode::FastStartUp,
+        _ => GyroscopePowerMode::Normal,
+    };
+    SensorPowerMode {
+        accel,
+        gyro,
+        magnet,
+    }
+}
+
+pub(crate) fn decode_status(status: u8) -> Status {
+    Status {
+        accel_data_ready: (status & BitFlags::DRDY_ACC) != 0,
+        gyro_data_ready: (status & BitFlags::DRDY_GYR) != 0,
+        magnet_data_ready: (status & BitFlags::DRDY_MAG) != 0,
+        nvm_ready: (status & BitFlags::NVM_RDY) != 0,
+        foc_ready: (status & BitFlags::FOC_RDY) != 0,
+        magnet_manual_op: (status & BitFlags::MAG_MAN_OP) != 0,
+        gyro_self_test_ok: (status & BitFlags::GYR_SELF_TEST_OK) != 0,
+    }
+}
+
+pub(crate) fn accel_power_mode_cmd(mode: AccelerometerPowerMode) -> u8 {
+    match mode {
+        AccelerometerPowerMode::Suspend => 0b0001_0000,
+        AccelerometerPowerMode::Normal => 0b0001_0001,
+        AccelerometerPowerMode::LowPower => 0b0001_0010,
+    }
+}
+
+pub(crate) fn gyro_power_mode_cmd(mode: GyroscopePowerMode) -> u8 {
+    match mode {
+        GyroscopePowerMode::Suspend => 0b0001_0100,
+        GyroscopePowerMode::Normal => 0b0001_0101,
+        GyroscopePowerMode::FastStartUp => 0b0001_0111,
+    }
+}
+
+pub(crate) fn magnet_power_mode_cmd(mode: MagnetometerPowerMode) -> u8 {
+    match mode {
+        MagnetometerPowerMode::Suspend => 0b0001_1000,
+        MagnetometerPowerMode::Normal => 0b0001_1001,
+        MagnetometerPowerMode::LowPower => 0b0001_1010,
+    }
+}
+
+pub(crate) fn check_chip_id<E>(id: u8) -> Result<(), Error<E>> {
+    if id == CHIP_ID {
+        Ok(())
+    } else {
+        Err(Error::InvalidChipId(id))
+    }
+}
+
+pub(crate) fn decode_interface_config(if_conf: u8, nv_conf: u8) -> InterfaceConfig {
+    let i2c_watchdog = if (nv_conf & BitFlags::I2C_WDT_EN) == 0 {
+        I2cWatchdog::Disabled
+    } else if (nv_conf & BitFlags::I2C_WDT_SEL) == 0 {
+        I2cWatchdog::Ms1
+    } else {
+        I2cWatchdog::Ms50
+    };
+    InterfaceConfig {
+        spi_3wire: (if_conf & BitFlags::SPI3) != 0,
+        i2c_watchdog,
+    }
+}
+
+/// Read-modify-writes of IF_CONF and NV_CONF done by `set_interface_config()`
+pub(crate) fn interface_config_ops(config: &InterfaceConfig) -> [Op; 2] {
+    [
+        Op::update(Register::IF_CONF, |v| if_conf_with_config(v, config)),
+        Op::update(Register::NV_CONF, |v| nv_conf_with_config(v, config)),
+    ]
+}
+
+pub(crate) fn if_conf_with_config(current: u8, config: &InterfaceConfig) -> u8 {
+    if config.spi_3wire {
+        current | BitFlags::SPI3
+    } else {
+        current & !BitFlags::SPI3
+    }
+}
+
+pub(crate) fn nv_conf_with_config(current: u8, config: &InterfaceConfig) -> u8 {
+    let current = current & !(BitFlags::I2C_WDT_EN | BitFlags::I2C_WDT_SEL);
+    match config.i2c_watchdog {
+        I2cWatchdog::Disabled => current,
+        I2cWatchdog::Ms1 => current | BitFlags::I2C_WDT_EN,
+        I2cWatchdog::Ms50 => current | BitFlags::I2C_WDT_EN | BitFlags::I2C_WDT_SEL,
+    }
 }
@@ -0,0 +1,373 @@
+//! FIFO frame parser
+//!
+//! The parser works on FIFO data read in header mode as returned by
+//! [`read_fifo()`](crate::Bmi160::read_fifo), by a DMA transfer or from a
+//! captured dump. It does not need a driver instance, does not copy the
+//! data and decodes the frames lazily.
+//!
+//! ```
+//! use bmi160::fifo::{FifoFrame, FifoFrames};
+//!
+//! let data = [
+//!     0x84, 1, 0, 2, 0, 3, 0, // accelerometer frame
+//!     0x44, 0x01, 0x02, 0x03, // sensor time frame
+//! ];
+//! let mut frames = FifoFrames::new(&data);
+//! match frames.next() {
+//!     Some(FifoFrame::Data(data)) => assert_eq!(data.accel.unwrap().x, 1),
+//!     _ => panic!(),
+//! }
+//! assert_eq!(frames.next(), Some(FifoFrame::SensorTime(0x030201)));
+//! assert_eq!(frames.next(), None);
+//! ```
+
+use crate::{
+    fifo_impl::FIFO_OVERREAD,
+    read_sensor_data::{get_sensor3d_data, EMPTY_DATA},
+    AccelerometerOdr, Data, GyroscopeOdr, MagnetometerData, SensorTime,
+};
+
+/// FIFO capacity in bytes
+const FIFO_CAPACITY: usize = 1024;
+
+/// Buffer length holding the whole FIFO content including the sensor time
+/// frame read by [`read_fifo()`](crate::Bmi160::read_fifo)
+pub const FIFO_BUFFER_LEN: usize = FIFO_CAPACITY + FIFO_OVERREAD;
+
+/// Frame decoded from the FIFO in header mode
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum FifoFrame {
+    /// Raw sensor data in the sensor axes. The time is never present.
+    Data(Data),
+    /// Frames were dropped because the FIFO was full
+    Skip(u8),
+    /// Sensor time at the moment the last data frame was read
+    SensorTime(u32),
+    /// The FIFO configuration changed
+    ConfigChange(u8),
+}
+
+const HEADER_MODE: u8 = 0b11 << 6;
+const HEADER_REGULAR: u8 = 0b10 << 6;
+const HEADER_CONTROL: u8 = 0b01 << 6;
+const HEADER_MAG: u8 = 1 << 4;
+const HEADER_GYR: u8 = 1 << 3;
+const HEADER_ACC: u8 = 1 << 2;
+const HEADER_END: u8 = 0x80;
+
+/// Iterator over the frames contained in FIFO data read in header mode
+///
+/// Iteration stops at the end of the data, at an incomplete frame or at an
+/// unknown header. An empty FIFO returns the end-of-data header `0x80`.
+///
+/// The data is raw and in the sensor axes. The axis remapping and the
+/// host-side calibration are not applied, see
+/// [`scale_data()`](crate::Bmi160::scale_data) and
+/// [`AxisRemap::remap_data()`](crate::AxisRemap::remap_data).
+#[derive(Debug, Clone)]
+pub struct FifoFrames<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FifoFrames<'a> {
+    /// Create a new iterator over FIFO data.
+    pub fn new(data: &'a [u8]) -> Self {
+        FifoFrames { data }
+    }
+
+    /// Remaining undecoded bytes
+    ///
+    /// After the iteration stopped, this starts with the incomplete frame
+    /// or the unknown header, if any.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl Iterator for FifoFrames<'_> {
+    type Item = FifoFrame;
+
+    fn next(&mut self) -> Option<FifoFrame> {
+        let (&header, payload) = self.data.split_first()?;
+        let (frame, len) = match header & HEADER_MODE {
+            HEADER_REGULAR if header != HEADER_END => {
+                let len = regular_frame_len(header);
+                if payload.len() < len {
+                    None
+                } else {
+                    Some((FifoFrame::Data(decode_regular_frame(header, payload)), len))
+                }
+            }
+            HEADER_CONTROL => decode_control_frame(header, payload),
+            _ => None,
+        }?;
+        self.data = &payload[len..];
+        Some(frame)
+    }
+}
+
+/// Sample period of the FIFO data frames
+///
+/// All sensors stored in the FIFO must use the same output data rate and
+/// down-sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct FifoTiming {
+    period: u32,
+}
+
+impl FifoTiming {
+    /// Timing of the frames from the accelerometer output data rate and the
+    /// FIFO down-sampling exponent, storing every 2^`downsampling` sample.
+    ///
+    /// The down-sampling exponent is limited to 7.
+    pub fn accel(odr: AccelerometerOdr, downsampling: u8) -> Self {
+        FifoTiming {
+            period: odr.period_ticks() << downsampling.min(7),
+        }
+    }
+
+    /// Timing of the frames from the gyroscope output data rate and the
+    /// FIFO down-sampling exponent, storing every 2^`downsampling` sample.
+    ///
+    /// The down-sampling exponent is limited to 7.
+    pub fn gyro(odr: GyroscopeOdr, downsampling: u8) -> Self {
+        FifoTiming {
+            period: odr.period_ticks() << downsampling.min(7),
+        }
+    }
+
+    /// Period between frames in sensor time ticks
+    pub fn period_ticks(&self) -> u32 {
+        self.period
+    }
+}
+
+/// Iterator over the samples contained in FIFO data read in header mode,
+/// each with its reconstructed sensor time
+///
+/// The data must end with the sensor time frame, which is returned by
+/// [`read_fifo()`](crate::Bmi160::read_fifo) when enabled in the
+/// [`FifoConfig`](crate::FifoConfig). The samples are taken on a grid of
+/// the frame period in the sensor time, so the time of each sample is
+/// computed backwards from the last sample before the sensor time frame,
+/// leaving a gap for the frames reported as skipped. Without a sensor time
+/// frame the samples have no time.
+///
+/// The samples are raw and in the sensor axes. The axis remapping and the
+/// host-side calibration are not applied, see
+/// [`scale_data()`](crate::Bmi160::scale_data).
+#[derive(Debug, Clone)]
+pub struct TimestampedFifo<'a> {
+    frames: FifoFrames<'a>,
+    period: u32,
+    next_time: Option<u32>,
+}
+
+impl<'a> TimestampedFifo<'a> {
+    /// Create a new iterator over FIFO data.
+    pub fn new(data: &'a [u8], timing: FifoTiming) -> Self {
+        let frames = FifoFrames::new(data);
+        let period = timing.period;
+        let mut slots: u32 = 0;
+        let mut next_time = None;
+        for frame in frames.clone() {
+            match frame {
+                FifoFrame::Data(_) => slots += 1,
+                FifoFrame::Skip(n) => slots += u32::from(n),
+                FifoFrame::SensorTime(time) => {
+                    if slots > 0 {
+                        let last = time & !(period - 1);
+                        next_time = Some(last.wrapping_sub((slots - 1).wrapping_mul(period)));
+                    }
+                    break;
+                }
+                FifoFrame::ConfigChange(_) => (),
+            }
+        }
+        TimestampedFifo {
+            frames,
+            period,
+            next_time,
+        }
+    }
+
+    fn advance(&mut self, slots: u32) {
+        self.next_time = self
+            .next_time
+            .map(|t| t.wrapping_add(slots.wrapping_mul(self.period)));
+    }
+}
+
+impl Iterator for TimestampedFifo<'_> {
+    type Item = Data;
+
+    fn next(&mut self) -> Option<Data> {
+        loop {
+            match self.frames.next()? {
+                FifoFrame::Data(mut data) => {
+                    data.time = self.next_time.map(|t| SensorTime(t).ticks());
+                    self.advance(1);
+                    return Some(data);
+                }
+                FifoFrame::Skip(n) => self.advance(u32::from(n)),
+                FifoFrame::SensorTime(_) => return None,
+                FifoFrame::ConfigChange(_) => (),
+            }
+        }
+    }
+}
+
+fn regular_frame_len(header: u8) -> usize {
+    let mut len = 0;
+    if (header & HEADER_MAG) != 0 {
+        len += 8;
+    }
+    if (header & HEADER_GYR) != 0 {
+        len += 6;
+    }
+    if (header & HEADER_ACC) != 0 {
+        len += 6;
+    }
+    len
+}
+
+fn decode_regular_frame(header: u8, mut payload: &[u8]) -> Data {
+    let mut result = EMPTY_DATA;
+    if (header & HEADER_MAG) != 0 {
+        result.magnet = Some(MagnetometerData {
+            axes: get_sensor3d_data(&payload[0..6]),
+            hall_resistence: u16::from(payload[6]) | (u16::from(payload[7]) << 8),
+        });
+        payload = &payload[8..];
+    }
+    if (header & HEADER_GYR) != 0 {
+        result.gyro = Some(get_sensor3d_data(&payload[0..6]));
+        payload = &payload[6..];
+    }
+    if (header & HEADER_ACC) != 0 {
+        result.accel = Some(get_sensor3d_data(&payload[0..6]));
+    }
+    result
+}
+
+fn decode_control_frame(header: u8, payload: &[u8]) -> Option<(FifoFrame, usize)> {
+    match (header >> 2) & 0b1111 {
+        0 => payload.first().map(|&n| (FifoFrame::Skip(n), 1)),
+        1 if payload.len() >= 3 => {
+            let time = u32::from(payload[0])
+                | (u32::from(payload[1]) << 8)
+                | (u32::from(payload[2]) << 16);
+            Some((FifoFrame::SensorTime(time), 3))
+        }
+        2 => payload.first().map(|&c| (FifoFrame::ConfigChange(c), 1)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sensor3DData;
+
+    #[test]
+    fn can_decode_frames() {
+        let data = [
+            0x8C, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, // gyro + accel
+            0x40, 7, // skip
+            0x84, 0xFF, 0xFF, 0, 0x80, 0, 0, // accel
+            0x48, 0x50, // config change
+            0x44, 0x01, 0x02, 0x03, // sensor time
+            0x80, 0x80,
+        ];
+        let frames: [Option<FifoFrame>; 6] = {
+            let mut it = FifoFrames::new(&data);
+            core::array::from_fn(|_| it.next())
+        };
+        let mut first = EMPTY_DATA;
+        first.gyro = Some(Sensor3DData { x: 1, y: 2, z: 3 });
+        first.accel = Some(Sensor3DData { x: 4, y: 5, z: 6 });
+        let mut second = EMPTY_DATA;
+        second.accel = Some(Sensor3DData {
+            x: -1,
+            y: -32768,
+            z: 0,
+        });
+        assert_eq!(
+            frames,
+            [
+                Some(FifoFrame::Data(first)),
+                Some(FifoFrame::Skip(7)),
+                Some(FifoFrame::Data(second)),
+                Some(FifoFrame::ConfigChange(0x50)),
+                Some(FifoFrame::SensorTime(0x030201)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_incomplete_frame() {
+        let mut frames = FifoFrames::new(&[0x84, 1, 0, 2]);
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.remaining(), &[0x84, 1, 0, 2]);
+    }
+
+    #[test]
+    fn can_reconstruct_times() {
+        let data = [
+            0x40, 2, // skip
+            0x84, 1, 0, 0, 0, 0, 0, // accel
+            0x48, 0x50, // config change
+            0x84, 2, 0, 0, 0, 0, 0, // accel
+            0x44, 0x30, 0x12, 0x00, // sensor time
+            0x84, 3, 0, 0, 0, 0, 0, // accel after the sensor time
+        ];
+        let timing = FifoTiming::accel(AccelerometerOdr::Hz100, 0);
+        assert_eq!(timing.period_ticks(), 256);
+        let times: [Option<Option<u32>>; 3] = {
+            let mut it = TimestampedFifo::new(&data, timing);
+            core::array::from_fn(|_| it.next().map(|d| d.time))
+        };
+        assert_eq!(times, [Some(Some(0x1100)), Some(Some(0x1200)), None]);
+    }
+
+    #[test]
+    fn reconstructs_times_across_wraparound() {
+        let data = [
+            0x84, 1, 0, 0, 0, 0, 0, // accel
+            0x40, 1, // skip
+            0x84, 2, 0, 0, 0, 0, 0, // accel
+            0x44, 0x0F, 0x00, 0x00, // sensor time
+        ];
+        let timing = FifoTiming::gyro(crate::GyroscopeOdr::Hz400, 1);
+        assert_eq!(timing.period_ticks(), 128);
+        let times: [Option<u32>; 2] = {
+            let mut it = TimestampedFifo::new(&data, timing);
+            core::array::from_fn(|_| it.next().and_then(|d| d.time))
+        };
+        assert_eq!(times, [Some(0xFF_FF00), Some(0)]);
+    }
+
+    #[test]
+    fn has_no_times_without_sensor_time_frame() {
+        let data = [0x84, 1, 0, 0, 0, 0, 0];
+        let timing = FifoTiming::accel(AccelerometerOdr::Hz100, 0);
+        let mut it = TimestampedFifo::new(&data, timing);
+        assert_eq!(it.next().map(|d| d.time), Some(None));
+        assert_eq!(it.next(), None);
+    }
+
+    #[test]
+    fn can_decode_magnet_frame() {
+        let data = [0x90, 1, 0, 2, 0, 3, 0, 0x34, 0x12];
+        let frame = FifoFrames::new(&data).next();
+        let mut expected = EMPTY_DATA;
+        expected.magnet = Some(MagnetometerData {
+            axes: Sensor3DData { x: 1, y: 2, z: 3 },
+            hall_resistence: 0x1234,
+        });
+        assert_eq!(frame, Some(FifoFrame::Data(expected)));
+    }
+}
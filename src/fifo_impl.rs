@@ -0,0 +1,90 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    sequence::Op,
+    BitFlags, Bmi160, Error, FifoConfig, Register,
+};
+
+/// Bytes read after the fill level to retrieve the sensor time frame
+pub(crate) const FIFO_OVERREAD: usize = 4;
+/// Command to clear the FIFO content
+pub(crate) const FIFO_FLUSH_CMD: u8 = 0xB0;
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Configure which data is stored in the FIFO and its watermark level.
+    ///
+    /// The FIFO is operated in header mode.
+    pub fn set_fifo_config(&mut self, config: FifoConfig) -> Result<(), Error<CommE>> {
+        self.run(fifo_config_ops(&config), &mut [])
+    }
+
+    /// Clear the FIFO content.
+    pub fn flush_fifo(&mut self) -> Result<(), Error<CommE>> {
+        self.iface.write_register(Register::CMD, FIFO_FLUSH_CMD)
+    }
+
+    /// Get the current FIFO fill level in bytes.
+    pub fn fifo_length(&mut self) -> Result<u16, Error<CommE>> {
+        let mut data = [0; 2];
+        self.run([FIFO_LENGTH_READ], &mut data)?;
+        Ok(decode_fifo_length(&data))
+    }
+
+    /// Read the FIFO content into the buffer and return the bytes read.
+    ///
+    /// A few bytes after the fill level are read as well so that the sensor
+    /// time frame is included if enabled. A buffer of
+    /// [`FIFO_BUFFER_LEN`](crate::fifo::FIFO_BUFFER_LEN) bytes can hold the
+    /// whole FIFO.
+    ///
+    /// The data is returned as stored in the FIFO, in the sensor axes and
+    /// without the axis remapping. See [`fifo`](crate::fifo) to decode it.
+    ///
+    /// Returns `Error::InvalidInputData` if the buffer is empty.
+    pub fn read_fifo<'b>(&mut self, buffer: &'b mut [u8]) -> Result<&'b [u8], Error<CommE>> {
+        let len = fifo_read_len(self.fifo_length()?, buffer.len())?;
+        self.iface
+            .read_burst(Register::FIFO_DATA, &mut buffer[..len])?;
+        Ok(&buffer[..len])
+    }
+}
+
+/// Read of the FIFO fill level registers
+pub(crate) const FIFO_LENGTH_READ: Op = Op::Read(Register::FIFO_LENGTH_0, 2);
+
+pub(crate) fn fifo_config_ops(config: &FifoConfig) -> [Op; 2] {
+    [
+        Op::Write(Register::FIFO_CONFIG_0, config.watermark),
+        Op::Write(Register::FIFO_CONFIG_1, fifo_config_1(config)),
+    ]
+}
+
+pub(crate) fn fifo_config_1(config: &FifoConfig) -> u8 {
+    let mut value = BitFlags::FIFO_HEADER_EN;
+    if config.accel {
+        value |= BitFlags::FIFO_ACC_EN;
+    }
+    if config.gyro {
+        value |= BitFlags::FIFO_GYR_EN;
+    }
+    if config.magnet {
+        value |= BitFlags::FIFO_MAG_EN;
+    }
+    if config.time {
+        value |= BitFlags::FIFO_TIME_EN;
+    }
+    value
+}
+
+pub(crate) fn decode_fifo_length(data: &[u8]) -> u16 {
+    u16::from(data[0]) | (u16::from(data[1] & BitFlags::FIFO_LENGTH_1) << 8)
+}
+
+pub(crate) fn fifo_read_len<E>(fifo_length: u16, buffer_len: usize) -> Result<usize, Error<E>> {
+    if buffer_len == 0 {
+        return Err(Error::InvalidInputData);
+    }
+    Ok((usize::from(fifo_length) + FIFO_OVERREAD).min(buffer_len))
+}
@@ -0,0 +1,276 @@
+//! Orientation fusion filter
+//!
+//! [`Madgwick`] estimates the orientation from the gyroscope and
+//! accelerometer data and, if available, the magnetometer data returned by
+//! [`data_scaled()`](crate::Bmi160::data_scaled). All sensors must use the
+//! same axes.
+//!
+//! ```no_run
+//! # use embedded_hal_mock::eh1::i2c::Mock;
+//! use bmi160::{fusion::Madgwick, Bmi160, SensorSelector, SensorTime, SlaveAddr};
+//!
+//! # let dev = Mock::new(&[]);
+//! let mut imu = Bmi160::new_with_i2c(dev, SlaveAddr::default());
+//! let mut filter = Madgwick::new(0.1);
+//! let mut last_time = None;
+//! loop {
+//!     let data = imu.data_scaled(SensorSelector::all()).unwrap();
+//!     if let (Some(last), Some(now)) = (last_time, data.time) {
+//!         filter.update(&data, SensorTime(now).wrapping_sub(SensorTime(last)));
+//!         let angles = filter.quaternion().euler();
+//!     }
+//!     last_time = data.time;
+//! }
+//! ```
+
+use crate::{DataScaled, Sensor3DDataScaled};
+
+/// Sensor time resolution in seconds
+const SENSORTIME_TICK_S: f32 = 39.0625e-6;
+
+/// Orientation quaternion
+///
+/// This rotates vectors from the earth frame (Z axis up) into the sensor
+/// frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Quaternion {
+    /// Scalar part
+    pub w: f32,
+    /// X component
+    pub x: f32,
+    /// Y component
+    pub y: f32,
+    /// Z component
+    pub z: f32,
+}
+
+/// Euler angles in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct EulerAngles {
+    /// Rotation around the X axis
+    pub roll: f32,
+    /// Rotation around the Y axis
+    pub pitch: f32,
+    /// Rotation around the Z axis
+    pub yaw: f32,
+}
+
+impl Quaternion {
+    /// Quaternion with the sensor frame equal to the earth frame
+    pub const IDENTITY: Quaternion = Quaternion {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Euler angles in degrees
+    pub fn euler(&self) -> EulerAngles {
+        let Quaternion { w, x, y, z } = *self;
+        let sin_pitch = (2.0 * (w * y - x * z)).clamp(-1.0, 1.0);
+        EulerAngles {
+            roll: libm::atan2f(w * x + y * z, 0.5 - x * x - y * y).to_degrees(),
+            pitch: libm::asinf(sin_pitch).to_degrees(),
+            yaw: libm::atan2f(x * y + w * z, 0.5 - y * y - z * z).to_degrees(),
+        }
+    }
+
+    /// Gravity direction in the sensor frame in g
+    pub fn gravity(&self) -> Sensor3DDataScaled {
+        let Quaternion { w, x, y, z } = *self;
+        Sensor3DDataScaled {
+            x: 2.0 * (x * z - w * y),
+            y: 2.0 * (w * x + y * z),
+            z: w * w - x * x - y * y + z * z,
+        }
+    }
+
+    fn normalized(self) -> Self {
+        let Quaternion { w, x, y, z } = self;
+        let norm = libm::sqrtf(w * w + x * x + y * y + z * z);
+        Quaternion {
+            w: w / norm,
+            x: x / norm,
+            y: y / norm,
+            z: z / norm,
+        }
+    }
+}
+
+/// Madgwick orientation filter
+///
+/// This uses the gyroscope and accelerometer data (6-axis) or additionally
+/// the magnetometer data (9-axis) whenever available.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Madgwick {
+    beta: f32,
+    q: Quaternion,
+}
+
+impl Madgwick {
+    /// Create a new filter.
+    ///
+    /// `beta` is the gain of the accelerometer and magnetometer correction.
+    /// Higher values converge faster but are more sensitive to noise and
+    /// linear acceleration. 0.1 is a common value.
+    pub fn new(beta: f32) -> Self {
+        Madgwick {
+            beta,
+            q: Quaternion::IDENTITY,
+        }
+    }
+
+    /// Current orientation
+    pub fn quaternion(&self) -> Quaternion {
+        self.q
+    }
+
+    /// Reset the orientation to the identity.
+    pub fn reset(&mut self) {
+        self.q = Quaternion::IDENTITY;
+    }
+
+    /// Update the orientation with data taken `sensortime_delta` sensor time
+    /// ticks (39.0625 µs) after the previous data.
+    ///
+    /// See [`SensorTime::wrapping_sub()`](crate::SensorTime::wrapping_sub).
+    pub fn update(&mut self, data: &DataScaled, sensortime_delta: u32) {
+        self.update_dt(data, sensortime_delta as f32 * SENSORTIME_TICK_S);
+    }
+
+    /// Update the orientation with data taken `dt` seconds after the
+    /// previous data.
+    ///
+    /// Data without gyroscope data is ignored.
+    pub fn update_dt(&mut self, data: &DataScaled, dt: f32) {
+        let gyro = match data.gyro {
+            Some(g) => [g.x, g.y, g.z].map(f32::to_radians),
+            None => return,
+        };
+        let Quaternion { w, x, y, z } = self.q;
+        // Rate of change of the quaternion from the gyroscope
+        let mut q_dot = [
+            0.5 * (-x * gyro[0] - y * gyro[1] - z * gyro[2]),
+            0.5 * (w * gyro[0] + y * gyro[2] - z * gyro[1]),
+            0.5 * (w * gyro[1] - x * gyro[2] + z * gyro[0]),
+            0.5 * (w * gyro[2] + x * gyro[1] - y * gyro[0]),
+        ];
+        let accel = data.accel.and_then(normalize);
+        let step = match (accel, data.magnet.and_then(normalize)) {
+            (Some(a), Some(m)) => Some(marg_step(self.q, a, m)),
+            (Some(a), None) => Some(imu_step(self.q, a)),
+            _ => None,
+        };
+        if let Some(s) = step {
+            let norm = libm::sqrtf(s.iter().map(|v| v * v).sum());
+            if norm > 0.0 {
+                for (q_dot, s) in q_dot.iter_mut().zip(s) {
+                    *q_dot -= self.beta * s / norm;
+                }
+            }
+        }
+        self.q = Quaternion {
+            w: w + q_dot[0] * dt,
+            x: x + q_dot[1] * dt,
+            y: y + q_dot[2] * dt,
+            z: z + q_dot[3] * dt,
+        }
+        .normalized();
+    }
+}
+
+fn normalize(v: Sensor3DDataScaled) -> Option<[f32; 3]> {
+    let norm = libm::sqrtf(v.x * v.x + v.y * v.y + v.z * v.z);
+    if norm > 0.0 {
+        Some([v.x / norm, v.y / norm, v.z / norm])
+    } else {
+        None
+    }
+}
+
+/// Gradient descent step from the accelerometer
+fn imu_step(q: Quaternion, a: [f32; 3]) -> [f32; 4] {
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let [ax, ay, az] = a;
+    let (q0q0, q1q1, q2q2, q3q3) = (q0 * q0, q1 * q1, q2 * q2, q3 * q3);
+    [
+        4.0 * q0 * q2q2 + 2.0 * q2 * ax + 4.0 * q0 * q1q1 - 2.0 * q1 * ay,
+        4.0 * q1 * q3q3 - 2.0 * q3 * ax + 4.0 * q0q0 * q1 - 2.0 * q0 * ay - 4.0 * q1
+            + 8.0 * q1 * q1q1
+            + 8.0 * q1 * q2q2
+            + 4.0 * q1 * az,
+        4.0 * q0q0 * q2 + 2.0 * q0 * ax + 4.0 * q2 * q3q3 - 2.0 * q3 * ay - 4.0 * q2
+            + 8.0 * q2 * q1q1
+            + 8.0 * q2 * q2q2
+            + 4.0 * q2 * az,
+        4.0 * q1q1 * q3 - 2.0 * q1 * ax + 4.0 * q2q2 * q3 - 2.0 * q2 * ay,
+    ]
+}
+
+/// Gradient descent step from the accelerometer and magnetometer
+fn marg_step(q: Quaternion, a: [f32; 3], m: [f32; 3]) -> [f32; 4] {
+    let Quaternion {
+        w: q0,
+        x: q1,
+        y: q2,
+        z: q3,
+    } = q;
+    let [ax, ay, az] = a;
+    let [mx, my, mz] = m;
+    let (q0q0, q0q1, q0q2, q0q3) = (q0 * q0, q0 * q1, q0 * q2, q0 * q3);
+    let (q1q1, q1q2, q1q3) = (q1 * q1, q1 * q2, q1 * q3);
+    let (q2q2, q2q3, q3q3) = (q2 * q2, q2 * q3, q3 * q3);
+
+    // Earth magnetic field direction
+    let hx = mx * q0q0 - 2.0 * q0 * my * q3
+        + 2.0 * q0 * mz * q2
+        + mx * q1q1
+        + 2.0 * q1 * my * q2
+        + 2.0 * q1 * mz * q3
+        - mx * q2q2
+        - mx * q3q3;
+    let hy = 2.0 * q0 * mx * q3 + my * q0q0 - 2.0 * q0 * mz * q1 + 2.0 * q1 * mx * q2 - my * q1q1
+        + my * q2q2
+        + 2.0 * q2 * mz * q3
+        - my * q3q3;
+    let bx2 = libm::sqrtf(hx * hx + hy * hy);
+    let bz2 = -2.0 * q0 * mx * q2 + 2.0 * q0 * my * q1 + mz * q0q0 + 2.0 * q1 * mx * q3 - mz * q1q1
+        + 2.0 * q2 * my * q3
+        - mz * q2q2
+        + mz * q3q3;
+    let (bx4, bz4) = (2.0 * bx2, 2.0 * bz2);
+
+    // Objective function terms
+    let fa_x = 2.0 * q1q3 - 2.0 * q0q2 - ax;
+    let fa_y = 2.0 * q0q1 + 2.0 * q2q3 - ay;
+    let fa_z = 1.0 - 2.0 * q1q1 - 2.0 * q2q2 - az;
+    let fm_x = bx2 * (0.5 - q2q2 - q3q3) + bz2 * (q1q3 - q0q2) - mx;
+    let fm_y = bx2 * (q1q2 - q0q3) + bz2 * (q0q1 + q2q3) - my;
+    let fm_z = bx2 * (q0q2 + q1q3) + bz2 * (0.5 - q1q1 - q2q2) - mz;
+    [
+        -2.0 * q2 * fa_x + 2.0 * q1 * fa_y - bz2 * q2 * fm_x
+            + (-bx2 * q3 + bz2 * q1) * fm_y
+            + bx2 * q2 * fm_z,
+        2.0 * q3 * fa_x + 2.0 * q0 * fa_y - 4.0 * q1 * fa_z
+            + bz2 * q3 * fm_x
+            + (bx2 * q2 + bz2 * q0) * fm_y
+            + (bx2 * q3 - bz4 * q1) * fm_z,
+        -2.0 * q0 * fa_x + 2.0 * q3 * fa_y - 4.0 * q2 * fa_z
+            + (-bx4 * q2 - bz2 * q0) * fm_x
+            + (bx2 * q1 + bz2 * q3) * fm_y
+            + (bx2 * q0 - bz4 * q2) * fm_z,
+        2.0 * q1 * fa_x
+            + 2.0 * q2 * fa_y
+            + (-bx4 * q3 + bz2 * q1) * fm_x
+            + (-bx2 * q0 + bz2 * q2) * fm_y
+            + bx2 * q1 * fm_z,
+    ]
+}
@@ -0,0 +1,359 @@
+use crate::{
+    axis_remap::round,
+    config::{decode_offsets, encode_offsets},
+    interface::{ReadData, WriteData},
+    read_sensor_data::scale_data,
+    sequence::Op,
+    AccelerometerRange, AxisRemap, Bmi160, Calibration, Data, DataScaled, Error, GyroscopeRange,
+    Register, Sensor3DDataScaled, SensorSelector,
+};
+
+/// Gyroscope offset register resolution in °/s
+pub(crate) const GYRO_OFFSET_LSB_DPS: f32 = 0.061;
+
+/// Stillness detection and bias estimation parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct StillnessConfig {
+    /// Number of samples evaluated together
+    pub window: u16,
+    /// Maximum accelerometer variance summed over the axes in g²
+    pub accel_variance: f32,
+    /// Maximum gyroscope rate magnitude of any sample in °/s
+    pub gyro_magnitude: f32,
+    /// Weight of each still window mean in the bias estimate, between 0 and 1
+    pub smoothing: f32,
+}
+
+impl Default for StillnessConfig {
+    fn default() -> Self {
+        StillnessConfig {
+            window: 50,
+            accel_variance: 1e-4,
+            gyro_magnitude: 5.0,
+            smoothing: 0.2,
+        }
+    }
+}
+
+/// Gyroscope bias estimator updated while the device is stationary
+///
+/// This is fed with [`update_gyro_bias()`](crate::Bmi160::update_gyro_bias),
+/// which reads the accelerometer and gyroscope data in the sensor axes as
+/// required by [`compensate_gyro_bias()`](crate::Bmi160::compensate_gyro_bias).
+/// Samples are evaluated in windows. The device is stationary during a
+/// window if the accelerometer variance and every gyroscope rate magnitude
+/// are below the thresholds. The gyroscope mean of each stationary window
+/// updates the bias estimate.
+///
+/// The estimate is in the axes of the data it was fed with, so feeding it
+/// with [`update()`](Self::update) and the output of
+/// [`data_scaled()`](crate::Bmi160::data_scaled) yields a bias in the board
+/// axes that must only be used with [`correct()`](Self::correct).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GyroBiasEstimator {
+    config: StillnessConfig,
+    stationary: bool,
+    bias: Option<[f32; 3]>,
+    count: u16,
+    accel_mean: [f32; 3],
+    accel_m2: [f32; 3],
+    gyro_sum: [f32; 3],
+}
+
+impl GyroBiasEstimator {
+    /// Create a new estimator.
+    pub fn new(config: StillnessConfig) -> Self {
+        GyroBiasEstimator {
+            config: StillnessConfig {
+                window: config.window.max(2),
+                ..config
+            },
+            stationary: false,
+            bias: None,
+            count: 0,
+            accel_mean: [0.0; 3],
+            accel_m2: [0.0; 3],
+            gyro_sum: [0.0; 3],
+        }
+    }
+
+    /// Add a sample and return whether the device is stationary.
+    ///
+    /// Samples without accelerometer or gyroscope data are ignored.
+    pub fn update(&mut self, data: &DataScaled) -> bool {
+        if let (Some(accel), Some(gyro)) = (data.accel, data.gyro) {
+            self.add_sample(accel, gyro);
+        }
+        self.stationary
+    }
+
+    /// Scale raw data without remapping or calibration and add it.
+    pub(crate) fn update_raw(
+        &mut self,
+        data: Data,
+        accel_range: AccelerometerRange,
+        gyro_range: GyroscopeRange,
+    ) -> bool {
+        self.update(&scale_data(
+            data,
+            accel_range,
+            gyro_range,
+            None,
+            &AxisRemap::IDENTITY,
+            &Calibration::IDENTITY,
+        ))
+    }
+
+    /// Whether the last complete window was stationary
+    pub fn is_stationary(&self) -> bool {
+        self.stationary
+    }
+
+    /// Current bias estimate in °/s, if a stationary window was seen
+    pub fn bias(&self) -> Option<[f32; 3]> {
+        self.bias
+    }
+
+    /// Subtract the bias estimate from gyroscope data.
+    pub fn correct(&self, gyro: Sensor3DDataScaled) -> Sensor3DDataScaled {
+        let [x, y, z] = self.bias.unwrap_or([0.0; 3]);
+        Sensor3DDataScaled {
+            x: gyro.x - x,
+            y: gyro.y - y,
+            z: gyro.z - z,
+        }
+    }
+
+    /// Discard the bias estimate and the current window.
+    pub fn reset(&mut self) {
+        *self = GyroBiasEstimator::new(self.config);
+    }
+
+    fn add_sample(&mut self, accel: Sensor3DDataScaled, gyro: Sensor3DDataScaled) {
+        let limit = self.config.gyro_magnitude;
+        if gyro.x * gyro.x + gyro.y * gyro.y + gyro.z * gyro.z > limit * limit {
+            self.stationary = false;
+            self.count = 0;
+            return;
+        }
+        if self.count == 0 {
+            self.accel_mean = [0.0; 3];
+            self.accel_m2 = [0.0; 3];
+            self.gyro_sum = [0.0; 3];
+        }
+        self.count += 1;
+        let n = f32::from(self.count);
+        let a = [accel.x, accel.y, accel.z];
+        let g = [gyro.x, gyro.y, gyro.z];
+        for axis in 0..3 {
+            let delta = a[axis] - self.accel_mean[axis];
+            self.accel_mean[axis] += delta / n;
+            self.accel_m2[axis] += delta * (a[axis] - self.accel_mean[axis]);
+            self.gyro_sum[axis] += g[axis];
+        }
+        if self.count == self.config.window {
+            self.finish_window(n);
+        }
+    }
+
+    fn finish_window(&mut self, n: f32) {
+        self.count = 0;
+        let variance: f32 = self.accel_m2.iter().sum::<f32>() / (n - 1.0);
+        self.stationary = variance <= self.config.accel_variance;
+        if !self.stationary {
+            return;
+        }
+        let mean = self.gyro_sum.map(|s| s / n);
+        self.bias = Some(match self.bias {
+            None => mean,
+            Some(bias) => {
+                let k = self.config.smoothing;
+                [0, 1, 2].map(|i| bias[i] + k * (mean[i] - bias[i]))
+            }
+        });
+    }
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Read the accelerometer and gyroscope and add the sample to the bias
+    /// estimator.
+    ///
+    /// The sample is read in the sensor axes, ignoring the axis remapping
+    /// and the host-side calibration. Returns whether the device is
+    /// stationary.
+    pub fn update_gyro_bias(
+        &mut self,
+        estimator: &mut GyroBiasEstimator,
+    ) -> Result<bool, Error<CommE>> {
+        let data = self.sensor_data(SensorSelector::new().accel().gyro())?;
+        Ok(estimator.update_raw(data, self.accel_range, self.gyro_range))
+    }
+
+    /// Compensate a gyroscope bias in °/s with the gyroscope offset registers
+    /// and enable the gyroscope offset compensation.
+    ///
+    /// The bias is subtracted from the current offsets, so a residual bias
+    /// estimated from compensated data can be passed again. The bias must be
+    /// in the sensor axes, as estimated with
+    /// [`update_gyro_bias()`](Self::update_gyro_bias), and the offsets
+    /// saturate at about ±31 °/s.
+    /// Returns the new offsets in units of 0.061 °/s.
+    pub fn compensate_gyro_bias(&mut self, bias: [f32; 3]) -> Result<[i16; 3], Error<CommE>> {
+        let mut data = [0; 7];
+        self.run([OFFSETS_READ], &mut data)?;
+        let (offsets, writes) = compensated_gyro_offsets(&data, bias)?;
+        self.run(writes, &mut [])?;
+        Ok(offsets)
+    }
+}
+
+/// Read of the offset registers
+pub(crate) const OFFSETS_READ: Op = Op::Read(Register::OFFSET_0, 7);
+
+/// Register writes setting the gyroscope offsets
+type GyroOffsetWrites = [Op; 4];
+
+/// New gyroscope offsets and the register writes setting them
+pub(crate) fn compensated_gyro_offsets<E>(
+    registers: &[u8],
+    bias: [f32; 3],
+) -> Result<([i16; 3], GyroOffsetWrites), Error<E>> {
+    let mut offsets = decode_offsets(registers);
+    let current = if offsets.gyro_enabled {
+        offsets.gyro
+    } else {
+        [0; 3]
+    };
+    for axis in 0..3 {
+        let correction = round(bias[axis] / GYRO_OFFSET_LSB_DPS) as i16;
+        offsets.gyro[axis] = current[axis].saturating_sub(correction).clamp(-512, 511);
+    }
+    offsets.gyro_enabled = true;
+    let values = encode_offsets(&offsets)?;
+    let writes = [
+        Op::Write(Register::OFFSET_0 + 3, values[3]),
+        Op::Write(Register::OFFSET_0 + 4, values[4]),
+        Op::Write(Register::OFFSET_0 + 5, values[5]),
+        Op::Write(Register::OFFSET_6, values[6]),
+    ];
+    Ok((offsets.gyro, writes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(accel: [f32; 3], gyro: [f32; 3]) -> DataScaled {
+        let v = |d: [f32; 3]| Sensor3DDataScaled {
+            x: d[0],
+            y: d[1],
+            z: d[2],
+        };
+        DataScaled {
+            accel: Some(v(accel)),
+            gyro: Some(v(gyro)),
+            magnet: None,
+            time: None,
+        }
+    }
+
+    fn config() -> StillnessConfig {
+        StillnessConfig {
+            window: 4,
+            smoothing: 0.5,
+            ..StillnessConfig::default()
+        }
+    }
+
+    #[test]
+    fn estimates_bias_when_still() {
+        let mut estimator = GyroBiasEstimator::new(config());
+        for _ in 0..3 {
+            assert!(!estimator.update(&data([0.0, 0.0, 1.0], [0.5, -0.25, 1.0])));
+        }
+        assert!(estimator.update(&data([0.0, 0.0, 1.0], [0.5, -0.25, 1.0])));
+        assert_eq!(estimator.bias(), Some([0.5, -0.25, 1.0]));
+        for _ in 0..4 {
+            estimator.update(&data([0.0, 0.0, 1.0], [1.5, -0.25, 1.0]));
+        }
+        assert_eq!(estimator.bias(), Some([1.0, -0.25, 1.0]));
+        let corrected = estimator.correct(Sensor3DDataScaled {
+            x: 1.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        assert_eq!(corrected.x, 0.0);
+        assert_eq!(corrected.y, 0.25);
+        assert_eq!(corrected.z, -1.0);
+    }
+
+    #[test]
+    fn rejects_accel_variance() {
+        let mut estimator = GyroBiasEstimator::new(config());
+        for i in 0..4 {
+            let z = if i % 2 == 0 { 1.1 } else { 0.9 };
+            assert!(!estimator.update(&data([0.0, 0.0, z], [0.5, 0.0, 0.0])));
+        }
+        assert_eq!(estimator.bias(), None);
+    }
+
+    #[test]
+    fn gyro_rate_restarts_window() {
+        let mut estimator = GyroBiasEstimator::new(config());
+        for _ in 0..4 {
+            estimator.update(&data([0.0, 0.0, 1.0], [0.5, 0.0, 0.0]));
+        }
+        assert!(estimator.is_stationary());
+        for _ in 0..3 {
+            estimator.update(&data([0.0, 0.0, 1.0], [0.0, 0.0, 0.0]));
+        }
+        assert!(!estimator.update(&data([0.0, 0.0, 1.0], [4.0, 4.0, 0.0])));
+        estimator.update(&data([0.0, 0.0, 1.0], [0.0, 0.0, 0.0]));
+        assert_eq!(estimator.bias(), Some([0.5, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn ignores_incomplete_data() {
+        let mut estimator = GyroBiasEstimator::new(config());
+        let mut sample = data([0.0, 0.0, 1.0], [0.5, 0.0, 0.0]);
+        sample.accel = None;
+        for _ in 0..4 {
+            estimator.update(&sample);
+        }
+        assert_eq!(estimator.bias(), None);
+        estimator.reset();
+        assert!(!estimator.is_stationary());
+    }
+
+    #[test]
+    fn adds_bias_to_enabled_offsets() {
+        // gyro offsets x = 10, y = -512, z = 511, enabled
+        let registers = [0, 0, 0, 10, 0, 0xFF, 0b1001_1000];
+        let (offsets, writes) =
+            compensated_gyro_offsets::<()>(&registers, [0.61, 1.0, -0.61]).unwrap();
+        assert_eq!(offsets, [0, -512, 511]);
+        assert_eq!(
+            writes,
+            [
+                Op::Write(Register::OFFSET_0 + 3, 0),
+                Op::Write(Register::OFFSET_0 + 4, 0),
+                Op::Write(Register::OFFSET_0 + 5, 0xFF),
+                Op::Write(Register::OFFSET_6, 0b1001_1000),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_disabled_offsets() {
+        let registers = [0, 0, 0, 10, 0, 0, 0b0100_0000];
+        let (offsets, writes) =
+            compensated_gyro_offsets::<()>(&registers, [0.0, -0.061, 0.0]).unwrap();
+        assert_eq!(offsets, [0, 1, 0]);
+        assert_eq!(writes[3], Op::Write(Register::OFFSET_6, 0b1100_0000));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::Sensor3DDataScaled;
+
+/// Inclination computed from the accelerometer data
+///
+/// The angles follow the same convention as the Euler angles of a device
+/// at rest: roll is the rotation around the X axis and pitch around the
+/// Y axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Inclination {
+    /// Rotation around the X axis in degrees, between -180 and 180
+    pub roll: f32,
+    /// Rotation around the Y axis in degrees, between -90 and 90
+    pub pitch: f32,
+    /// Angle between the Z axis and the vertical in degrees, between 0 and 180
+    pub tilt: f32,
+    /// Confidence between 0 and 1
+    ///
+    /// This is 1 when the acceleration magnitude is exactly 1 g and drops to
+    /// 0 as it deviates from 1 g by the tolerance or more.
+    pub confidence: f32,
+}
+
+impl Inclination {
+    /// Compute the inclination of a single accelerometer sample in g.
+    ///
+    /// `tolerance` is the deviation from 1 g in g at which the confidence
+    /// drops to 0.
+    pub fn from_accel(accel: Sensor3DDataScaled, tolerance: f32) -> Self {
+        let Sensor3DDataScaled { x, y, z } = accel;
+        let horizontal = libm::sqrtf(x * x + y * y);
+        let magnitude = libm::sqrtf(x * x + y * y + z * z);
+        let confidence = if tolerance > 0.0 {
+            1.0 - libm::fabsf(magnitude - 1.0) / tolerance
+        } else {
+            0.0
+        };
+        Inclination {
+            roll: libm::atan2f(y, z).to_degrees(),
+            pitch: libm::atan2f(-x, libm::sqrtf(y * y + z * z)).to_degrees(),
+            tilt: libm::atan2f(horizontal, z).to_degrees(),
+            confidence: confidence.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Accelerometer-only inclinometer for static applications
+///
+/// Averages the last `N` accepted accelerometer samples in g. A sample is
+/// rejected as motion if its magnitude deviates from 1 g by more than the
+/// motion threshold or if any axis changed by more than the motion
+/// threshold since the previous sample. Motion discards the average, as
+/// does an accepted sample with any axis further than the motion threshold
+/// from the average, so that only the new orientation is reported.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Inclinometer<const N: usize> {
+    motion_threshold: f32,
+    samples: [[f32; 3]; N],
+    len: usize,
+    next: usize,
+    previous: Option<[f32; 3]>,
+}
+
+impl<const N: usize> Inclinometer<N> {
+    /// Create a new inclinometer.
+    ///
+    /// `motion_threshold` in g is also the tolerance of the confidence.
+    pub fn new(motion_threshold: f32) -> Self {
+        Inclinometer {
+            motion_threshold,
+            samples: [[0.0; 3]; N],
+            len: 0,
+            next: 0,
+            previous: None,
+        }
+    }
+
+    /// Add an accelerometer sample in g.
+    ///
+    /// Returns whether the sample was accepted into the average.
+    /// The average is discarded on motion or on an orientation change.
+    pub fn add_sample(&mut self, sample: Sensor3DDataScaled) -> bool {
+        let v = [sample.x, sample.y, sample.z];
+        let moved = match self.previous {
+            Some(p) => p
+                .iter()
+                .zip(v)
+                .any(|(p, v)| libm::fabsf(v - p) > self.motion_threshold),
+            None => false,
+        };
+        self.previous = Some(v);
+        let magnitude = libm::sqrtf(v.iter().map(|v| v * v).sum());
+        if N == 0 || moved || libm::fabsf(magnitude - 1.0) > self.motion_threshold {
+            self.discard_samples();
+            return false;
+        }
+        if let Some(average) = self.average() {
+            let average = [average.x, average.y, average.z];
+            if average
+                .iter()
+                .zip(v)
+                .any(|(a, v)| libm::fabsf(v - a) > self.motion_threshold)
+            {
+                self.discard_samples();
+            }
+        }
+        self.samples[self.next] = v;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        true
+    }
+
+    /// Number of samples in the average
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no sample has been accepted
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the averaging window is full
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Discard all samples.
+    pub fn clear(&mut self) {
+        self.discard_samples();
+        self.previous = None;
+    }
+
+    fn discard_samples(&mut self) {
+        self.len = 0;
+        self.next = 0;
+    }
+
+    /// Average acceleration in g, if any sample was accepted
+    pub fn average(&self) -> Option<Sensor3DDataScaled> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut sum = [0.0; 3];
+        for sample in &self.samples[..self.len] {
+            for (sum, value) in sum.iter_mut().zip(sample) {
+                *sum += value;
+            }
+        }
+        let [x, y, z] = sum.map(|s| s / self.len as f32);
+        Some(Sensor3DDataScaled { x, y, z })
+    }
+
+    /// Inclination of the average acceleration, if any sample was accepted
+    pub fn inclination(&self) -> Option<Inclination> {
+        self.average()
+            .map(|a| Inclination::from_accel(a, self.motion_threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f32, y: f32, z: f32) -> Sensor3DDataScaled {
+        Sensor3DDataScaled { x, y, z }
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn computes_angles() {
+        let level = Inclination::from_accel(v(0.0, 0.0, 1.0), 0.1);
+        assert_eq!(
+            level,
+            Inclination {
+                roll: 0.0,
+                pitch: 0.0,
+                tilt: 0.0,
+                confidence: 1.0
+            }
+        );
+        let rolled = Inclination::from_accel(v(0.0, 0.5, 0.866_025_4), 0.1);
+        assert_near(rolled.roll, 30.0);
+        assert_near(rolled.pitch, 0.0);
+        assert_near(rolled.tilt, 30.0);
+        let pitched = Inclination::from_accel(v(-0.6, 0.0, 0.8), 0.1);
+        assert_near(pitched.pitch, 36.8699);
+        assert_near(pitched.roll, 0.0);
+        assert_near(pitched.tilt, 36.8699);
+        let upside_down = Inclination::from_accel(v(0.0, 0.0, -1.0), 0.1);
+        assert_near(upside_down.tilt, 180.0);
+    }
+
+    #[test]
+    fn confidence_drops_with_magnitude_error() {
+        assert_near(
+            Inclination::from_accel(v(0.0, 0.0, 1.05), 0.1).confidence,
+            0.5,
+        );
+        assert_near(
+            Inclination::from_accel(v(0.0, 0.0, 0.95), 0.1).confidence,
+            0.5,
+        );
+        assert_eq!(
+            Inclination::from_accel(v(0.0, 0.0, 1.5), 0.1).confidence,
+            0.0
+        );
+    }
+
+    #[test]
+    fn averages_window() {
+        let mut inclinometer = Inclinometer::<2>::new(0.1);
+        assert_eq!(inclinometer.inclination(), None);
+        assert!(inclinometer.add_sample(v(0.0, 0.0, 1.0)));
+        assert!(inclinometer.add_sample(v(0.0, 0.02, 1.0)));
+        assert!(inclinometer.is_full());
+        assert!(inclinometer.add_sample(v(0.0, 0.04, 1.0)));
+        assert_eq!(inclinometer.len(), 2);
+        let average = inclinometer.average().unwrap();
+        assert_near(average.y, 0.03);
+        let inclination = inclinometer.inclination().unwrap();
+        assert_near(inclination.roll, libm::atan2f(0.03, 1.0).to_degrees());
+    }
+
+    #[test]
+    fn rejects_motion() {
+        let mut inclinometer = Inclinometer::<4>::new(0.1);
+        assert!(inclinometer.add_sample(v(0.0, 0.0, 1.0)));
+        // Magnitude deviates from 1 g
+        assert!(!inclinometer.add_sample(v(0.0, 0.0, 1.15)));
+        // Sudden change since the previous sample
+        assert!(!inclinometer.add_sample(v(0.0, 0.5, 0.866)));
+        assert!(inclinometer.is_empty());
+        // At rest in the new position
+        assert!(inclinometer.add_sample(v(0.0, 0.5, 0.866)));
+        assert!(inclinometer.add_sample(v(0.0, 0.5, 0.866)));
+        assert_eq!(inclinometer.len(), 2);
+        assert_near(inclinometer.inclination().unwrap().roll, 30.0);
+        inclinometer.clear();
+        assert!(inclinometer.is_empty());
+    }
+
+    #[test]
+    fn restarts_average_on_slow_orientation_change() {
+        let mut inclinometer = Inclinometer::<8>::new(0.1);
+        let mut y = 0.0;
+        while y < 0.5 {
+            let sample = v(0.0, y, libm::sqrtf(1.0 - y * y));
+            assert!(inclinometer.add_sample(sample));
+            y += 0.05;
+        }
+        assert!(inclinometer.len() < 8);
+        let roll = inclinometer.inclination().unwrap().roll;
+        assert!(roll > 20.0, "{}", roll);
+    }
+}
@@ -1,7 +1,10 @@
 //! I2C/SPI interfaces
 
 use crate::{private, Error};
-use embedded_hal::{i2c, spi::SpiDevice};
+use embedded_hal::{
+    i2c,
+    spi::{Operation, SpiDevice},
+};
 
 const I2C_DEV_BASE_ADDR: u8 = 0x68;
 
@@ -96,6 +99,8 @@ pub trait ReadData: private::Sealed {
     fn read_register(&mut self, register: u8) -> Result<u8, Self::Error>;
     /// Read some data. The first element corresponds to the starting address.
     fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error>;
+    /// Read some data starting at an address into the whole buffer
+    fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error>;
 }
 
 impl<I2C, E> ReadData for I2cInterface<I2C>
@@ -119,6 +124,13 @@ where
             .write_read(addr, &[payload[0]], &mut payload[1..len])
             .map_err(Error::Comm)
     }
+
+    fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.address;
+        self.i2c
+            .write_read(addr, &[register], data)
+            .map_err(Error::Comm)
+    }
 }
 
 impl<SPI, CommE> ReadData for SpiInterface<SPI>
@@ -137,6 +149,138 @@ where
         self.spi.transfer_in_place(payload).map_err(Error::Comm)?;
         Ok(())
     }
+
+    fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi
+            .transaction(&mut [Operation::Write(&[register + 0x80]), Operation::Read(data)])
+            .map_err(Error::Comm)
+    }
+}
+
+/// Asynchronously write data
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "async", allow(async_fn_in_trait))]
+pub trait AsyncWriteData: private::Sealed {
+    /// Error type
+    type Error;
+    /// Write to an u8 register
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error>;
+    /// Write data. The first element corresponds to the starting address.
+    async fn write_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "async")]
+impl<I2C, E> AsyncWriteData for I2cInterface<I2C>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+{
+    type Error = Error<E>;
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error> {
+        let payload: [u8; 2] = [register, data];
+        let addr = self.address;
+        self.i2c.write(addr, &payload).await.map_err(Error::Comm)
+    }
+
+    async fn write_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.address;
+        self.i2c.write(addr, payload).await.map_err(Error::Comm)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI, CommE> AsyncWriteData for SpiInterface<SPI>
+where
+    SPI: embedded_hal_async::spi::SpiDevice<u8, Error = CommE>,
+{
+    type Error = Error<CommE>;
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error> {
+        let payload: [u8; 2] = [register, data];
+        self.spi.write(&payload).await.map_err(Error::Comm)
+    }
+
+    async fn write_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi.write(payload).await.map_err(Error::Comm)
+    }
+}
+
+/// Asynchronously read data
+#[cfg(feature = "async")]
+#[cfg_attr(feature = "async", allow(async_fn_in_trait))]
+pub trait AsyncReadData: private::Sealed {
+    /// Error type
+    type Error;
+    /// Read an u8 register
+    async fn read_register(&mut self, register: u8) -> Result<u8, Self::Error>;
+    /// Read some data. The first element corresponds to the starting address.
+    async fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error>;
+    /// Read some data starting at an address into the whole buffer
+    async fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "async")]
+impl<I2C, E> AsyncReadData for I2cInterface<I2C>
+where
+    I2C: embedded_hal_async::i2c::I2c<Error = E>,
+{
+    type Error = Error<E>;
+    async fn read_register(&mut self, register: u8) -> Result<u8, Self::Error> {
+        let mut data = [0];
+        let addr = self.address;
+        self.i2c
+            .write_read(addr, &[register], &mut data)
+            .await
+            .map_err(Error::Comm)
+            .and(Ok(data[0]))
+    }
+
+    async fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error> {
+        let len = payload.len();
+        let addr = self.address;
+        self.i2c
+            .write_read(addr, &[payload[0]], &mut payload[1..len])
+            .await
+            .map_err(Error::Comm)
+    }
+
+    async fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error> {
+        let addr = self.address;
+        self.i2c
+            .write_read(addr, &[register], data)
+            .await
+            .map_err(Error::Comm)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<SPI, CommE> AsyncReadData for SpiInterface<SPI>
+where
+    SPI: embedded_hal_async::spi::SpiDevice<u8, Error = CommE>,
+{
+    type Error = Error<CommE>;
+    async fn read_register(&mut self, register: u8) -> Result<u8, Self::Error> {
+        let mut data = [register + 0x80, 0];
+        self.spi
+            .transfer_in_place(&mut data)
+            .await
+            .map_err(Error::Comm)?;
+        Ok(data[1])
+    }
+
+    async fn read_data(&mut self, payload: &mut [u8]) -> Result<(), Self::Error> {
+        payload[0] += 0x80;
+        self.spi
+            .transfer_in_place(payload)
+            .await
+            .map_err(Error::Comm)?;
+        Ok(())
+    }
+
+    async fn read_burst(&mut self, register: u8, data: &mut [u8]) -> Result<(), Self::Error> {
+        self.spi
+            .transaction(&mut [Operation::Write(&[register + 0x80]), Operation::Read(data)])
+            .await
+            .map_err(Error::Comm)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,97 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    sequence::Op,
+    BitFlags, Bmi160, Error, InterruptPin, InterruptPinConfig, InterruptSource, Register,
+};
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Configure the electrical behavior of an interrupt pin.
+    pub fn set_interrupt_pin_config(
+        &mut self,
+        pin: InterruptPin,
+        config: InterruptPinConfig,
+    ) -> Result<(), Error<CommE>> {
+        self.run([interrupt_pin_config_op(pin, &config)], &mut [])
+    }
+
+    /// Enable an interrupt source and map it to an interrupt pin.
+    pub fn enable_interrupt(
+        &mut self,
+        source: InterruptSource,
+        pin: InterruptPin,
+    ) -> Result<(), Error<CommE>> {
+        self.run(enable_interrupt_ops(source, pin), &mut [])
+    }
+
+    /// Disable an interrupt source.
+    pub fn disable_interrupt(&mut self, source: InterruptSource) -> Result<(), Error<CommE>> {
+        self.run([disable_interrupt_op(source)], &mut [])
+    }
+}
+
+pub(crate) fn interrupt_pin_config_op(pin: InterruptPin, config: &InterruptPinConfig) -> Op {
+    Op::update(Register::INT_OUT_CTRL, |v| {
+        int_out_ctrl_with_config(v, pin, config)
+    })
+}
+
+/// Map the source to the pin, then enable it.
+pub(crate) fn enable_interrupt_ops(source: InterruptSource, pin: InterruptPin) -> [Op; 2] {
+    [
+        Op::update(Register::INT_MAP_1, |v| int_map_1_with(v, source, pin)),
+        Op::update(Register::INT_EN_1, |v| v | int_en_1_flag(source)),
+    ]
+}
+
+pub(crate) fn disable_interrupt_op(source: InterruptSource) -> Op {
+    Op::update(Register::INT_EN_1, |v| v & !int_en_1_flag(source))
+}
+
+pub(crate) fn int_out_ctrl_with_config(
+    current: u8,
+    pin: InterruptPin,
+    config: &InterruptPinConfig,
+) -> u8 {
+    let mut value = 0;
+    if config.edge_triggered {
+        value |= BitFlags::INT1_EDGE_CTRL;
+    }
+    if config.active_high {
+        value |= BitFlags::INT1_LVL;
+    }
+    if config.open_drain {
+        value |= BitFlags::INT1_OD;
+    }
+    if config.output_enabled {
+        value |= BitFlags::INT1_OUTPUT_EN;
+    }
+    match pin {
+        InterruptPin::Int1 => (current & 0xF0) | value,
+        InterruptPin::Int2 => (current & 0x0F) | (value << 4),
+    }
+}
+
+pub(crate) fn int_en_1_flag(source: InterruptSource) -> u8 {
+    match source {
+        InterruptSource::DataReady => BitFlags::INT_DRDY_EN,
+        InterruptSource::FifoWatermark => BitFlags::INT_FWM_EN,
+        InterruptSource::FifoFull => BitFlags::INT_FFULL_EN,
+    }
+}
+
+/// Map the source to the given pin and unmap it from the other one.
+pub(crate) fn int_map_1_with(current: u8, source: InterruptSource, pin: InterruptPin) -> u8 {
+    let int1 = match source {
+        InterruptSource::DataReady => BitFlags::INT1_DRDY,
+        InterruptSource::FifoWatermark => BitFlags::INT1_FWM,
+        InterruptSource::FifoFull => BitFlags::INT1_FFULL,
+    };
+    let int2 = int1 >> 4;
+    match pin {
+        InterruptPin::Int1 => (current & !int2) | int1,
+        InterruptPin::Int2 => (current & !int1) | int2,
+    }
+}
@@ -5,16 +5,62 @@
 //!
 //! This driver allows you to:
 //! - Get the latest sensor data. See: [`data()`].
+//! - Get the latest sensor data in SI units with unit newtypes. See: [`data_si()`].
+//! - Get the latest sensor data in milli-g and milli-°/s computed with integer math. See: [`data_milli()`].
 //! - Set the accelerometer, gyroscope and magnetometer power mode. See: [`set_accel_power_mode()`].
 //! - Get the sensor status. See: [`status()`].
 //! - Get power mode. See: [`power_mode()`].
 //! - Get chip ID. See: [`chip_id()`].
+//! - Activate SPI mode and verify the chip ID. See: [`init()`].
+//! - Write and read back the whole device configuration. See: [`configure()`] and [`read_config()`].
+//! - Dump, print and restore the register map. See: [`dump_registers()`] and [`restore_registers()`].
+//! - Transform the data into the board axes for rotated mountings. See: [`set_axis_remap()`].
+//! - Apply a host-side bias, scale and misalignment calibration stored as bytes. See: [`set_calibration()`].
+//! - Calibrate the accelerometer with the six-position method. See: [`capture_accel_face()`].
+//! - Estimate the gyroscope bias while stationary and compensate it with the offset registers. See: [`GyroBiasEstimator`], [`update_gyro_bias()`] and [`compensate_gyro_bias()`].
+//! - Fit the magnetometer hard-iron offset and soft-iron matrix to samples collected while rotating (`calibration` feature). See: `MagnetCalibrator`.
+//! - Read consecutive samples by polling the data-ready flags, reporting duplicated and missed samples. See: [`read_samples()`].
+//! - Convert the sensor time to durations and extend it into a monotonic 64-bit timestamp. See: [`SensorTime`] and [`SensorTimeExtender`].
+//! - Synchronize the sensor time with a host clock, estimating the offset and drift. See: [`ClockSync`] and [`sync_clock()`].
+//! - Compute the pitch, roll and tilt of a static device from the averaged accelerometer data with motion rejection (`fusion` feature). See: `Inclinometer`.
+//! - Estimate the orientation with a 6-axis or 9-axis Madgwick filter (`fusion` feature). See: `fusion`.
+//! - Enforce the accelerometer and gyroscope power modes at compile time. See: [`typestate`].
+//! - Configure 3-wire SPI and the I2C watchdog. See: [`set_interface_config()`].
+//! - Access the device connected to the secondary magnetometer interface. See: [`read_magnet_register()`].
+//! - Set up a BMM150, AK09916 or other magnetometer connected to the secondary interface. See: [`setup_magnetometer()`].
+//! - Set the magnetometer output data rate and read configuration. See: [`set_magnet_config()`].
+//! - Configure and read the FIFO. See: [`set_fifo_config()`] and [`read_fifo()`].
+//! - Reconstruct the sensor time of each FIFO sample. See: [`fifo::TimestampedFifo`].
+//! - Parse FIFO data from any buffer without a driver instance. See: [`fifo`].
+//! - Configure the interrupt pins and the data-ready and FIFO interrupts. See: [`enable_interrupt()`].
+//! - Await new samples signaled on an interrupt pin (`async` feature). See: `InterruptStream`.
 //!
 //! [`data()`]: struct.Bmi160.html#method.data
+//! [`data_si()`]: struct.Bmi160.html#method.data_si
+//! [`data_milli()`]: struct.Bmi160.html#method.data_milli
 //! [`set_accel_power_mode()`]: struct.Bmi160.html#method.set_accel_power_mode
 //! [`status()`]: struct.Bmi160.html#method.status
 //! [`power_mode()`]: struct.Bmi160.html#method.power_mode
 //! [`chip_id()`]: struct.Bmi160.html#method.chip_id
+//! [`init()`]: struct.Bmi160.html#method.init
+//! [`configure()`]: struct.Bmi160.html#method.configure
+//! [`read_config()`]: struct.Bmi160.html#method.read_config
+//! [`dump_registers()`]: struct.Bmi160.html#method.dump_registers
+//! [`restore_registers()`]: struct.Bmi160.html#method.restore_registers
+//! [`read_samples()`]: struct.Bmi160.html#method.read_samples
+//! [`set_axis_remap()`]: struct.Bmi160.html#method.set_axis_remap
+//! [`set_calibration()`]: struct.Bmi160.html#method.set_calibration
+//! [`capture_accel_face()`]: struct.Bmi160.html#method.capture_accel_face
+//! [`update_gyro_bias()`]: struct.Bmi160.html#method.update_gyro_bias
+//! [`compensate_gyro_bias()`]: struct.Bmi160.html#method.compensate_gyro_bias
+//! [`sync_clock()`]: struct.Bmi160.html#method.sync_clock
+//! [`set_interface_config()`]: struct.Bmi160.html#method.set_interface_config
+//! [`read_magnet_register()`]: struct.Bmi160.html#method.read_magnet_register
+//! [`setup_magnetometer()`]: struct.Bmi160.html#method.setup_magnetometer
+//! [`set_magnet_config()`]: struct.Bmi160.html#method.set_magnet_config
+//! [`set_fifo_config()`]: struct.Bmi160.html#method.set_fifo_config
+//! [`read_fifo()`]: struct.Bmi160.html#method.read_fifo
+//! [`enable_interrupt()`]: struct.Bmi160.html#method.enable_interrupt
 //!
 //! <!-- TODO
 //! [Introductory blog post](TODO)
@@ -80,6 +126,8 @@
 //! # fn main() {
 //! let spi = hal::SpidevDevice::open("/dev/spidev0.0").unwrap();
 //! let mut imu = Bmi160::new_with_spi(spi);
+//! // Switch the device to SPI mode
+//! imu.init().unwrap();
 //! let id = imu.chip_id().unwrap_or(0);
 //! println!("Chip ID: {}", id);
 //! # }
@@ -115,22 +163,72 @@
 //! ## Features
 //!
 //! - `defmt-03`: derives `defmt::Format` for public data types.
+//! - `async`: provides `Bmi160Async`, an asynchronous driver based on
+//!   the `embedded-hal-async` traits, and `InterruptStream`. This requires
+//!   Rust 1.75 or later.
+//! - `calibration`: provides `MagnetCalibrator`, the magnetometer
+//!   ellipsoid fit.
+//! - `fusion`: provides the `fusion` module with a Madgwick orientation
+//!   filter and `Inclinometer`.
 
 #![deny(unsafe_code, missing_docs)]
 #![no_std]
 
+mod accel_calibration;
+pub use crate::accel_calibration::{AccelCalibration, CaptureStatus, Face, SixPositionCalibration};
 mod device_impl;
 pub mod interface;
 mod types;
 pub use crate::interface::SlaveAddr;
 pub use crate::types::{
-    AccelerometerPowerMode, AccelerometerRange, Data, DataScaled, Error, GyroscopePowerMode,
-    GyroscopeRange, MagnetometerData, MagnetometerPowerMode, Sensor3DData, Sensor3DDataScaled,
-    SensorPowerMode, SensorSelector, Status,
+    AccelerometerBandwidth, AccelerometerConfig, AccelerometerOdr, AccelerometerPowerMode,
+    AccelerometerRange, Bmm150Preset, Config, Data, DataMilli, DataScaled, DataSi, Error,
+    FifoConfig, GyroscopeBandwidth, GyroscopeConfig, GyroscopeOdr, GyroscopePowerMode,
+    GyroscopeRange, I2cWatchdog, InterfaceConfig, InterruptConfig, InterruptPin,
+    InterruptPinConfig, InterruptSource, MagnetometerConfig, MagnetometerData, MagnetometerOdr,
+    MagnetometerPowerMode, MagnetometerReadBurst, MetersPerSecondSquared, Microtesla, Offsets,
+    RadiansPerSecond, SampleReport, Sensor3DData, Sensor3DDataMilli, Sensor3DDataScaled,
+    Sensor3DDataSi, SensorPowerMode, SensorSelector, Status,
 };
 mod register_address;
 use crate::register_address::{BitFlags, Register};
+#[cfg(feature = "async")]
+mod asynch;
+mod axis_remap;
+pub use crate::axis_remap::{AxisRemap, InvalidAxisRemap, SignedAxis};
+mod calibration;
+pub use crate::calibration::{Calibration, SensorCalibration};
+mod clock_sync;
+pub use crate::clock_sync::{ClockSync, HostTime};
+mod config;
+pub mod fifo;
+mod fifo_impl;
+#[cfg(feature = "fusion")]
+pub mod fusion;
+mod gyro_bias;
+pub use crate::gyro_bias::{GyroBiasEstimator, StillnessConfig};
+#[cfg(feature = "fusion")]
+mod inclinometer;
+#[cfg(feature = "fusion")]
+pub use crate::inclinometer::{Inclination, Inclinometer};
+mod interrupt_impl;
+#[cfg(feature = "calibration")]
+mod magnet_calibration;
+#[cfg(feature = "calibration")]
+pub use crate::magnet_calibration::{MagnetCalibrator, MagnetFit};
+mod magnet_interface;
+pub mod magnetometer;
 mod read_sensor_data;
+mod register_dump;
+pub use crate::register_dump::{DecodedRegister, RegisterDump, RegisterEntry};
+mod sensor_time;
+mod sequence;
+pub use crate::sensor_time::{SensorTime, SensorTimeExtender};
+#[cfg(feature = "async")]
+mod stream;
+pub mod typestate;
+#[cfg(feature = "async")]
+pub use crate::stream::{InterruptStream, SampleBatch, StreamTrigger};
 
 /// BMI160 device driver
 #[derive(Debug)]
@@ -139,6 +237,27 @@ pub struct Bmi160<DI> {
     iface: DI,
     accel_range: AccelerometerRange,
     gyro_range: GyroscopeRange,
+    magnet_conversion: Option<magnetometer::Conversion>,
+    axis_remap: AxisRemap,
+    calibration: Calibration,
+}
+
+/// BMI160 asynchronous device driver
+///
+/// This offers the same functionality as [`Bmi160`] as `async` methods
+/// based on the [`embedded-hal-async`] traits.
+///
+/// [`embedded-hal-async`]: https://docs.rs/embedded-hal-async
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct Bmi160Async<DI> {
+    /// Digital interface: I2C or SPI
+    iface: DI,
+    accel_range: AccelerometerRange,
+    gyro_range: GyroscopeRange,
+    magnet_conversion: Option<magnetometer::Conversion>,
+    axis_remap: AxisRemap,
+    calibration: Calibration,
 }
 
 mod private {
@@ -0,0 +1,390 @@
+use crate::{
+    interface::{ReadData, WriteData},
+    Bmi160, Error, Sensor3DDataScaled, SensorCalibration, SensorSelector,
+};
+
+/// Minimum number of samples for an ellipsoid fit
+const MIN_SAMPLES: usize = 9;
+/// Jacobi eigenvalue algorithm sweeps
+const JACOBI_SWEEPS: usize = 16;
+
+/// Result of a magnetometer ellipsoid fit
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct MagnetFit {
+    /// Hard-iron offset as bias and soft-iron correction as matrix
+    pub calibration: SensorCalibration,
+    /// Magnitude of the corrected field in µT
+    pub field_strength: f32,
+    /// RMS deviation of the corrected samples from the field strength,
+    /// relative to the field strength
+    pub residual: f32,
+}
+
+/// Magnetometer hard-iron and soft-iron calibration
+///
+/// Samples in µT are collected while the device is rotated in all
+/// directions and stored in a buffer of `N` samples. Samples closer than a
+/// minimum distance to a stored sample are rejected so that the buffer
+/// covers the whole ellipsoid. An ellipsoid is then fitted to the samples.
+///
+/// The samples must be in the sensor axes and without magnetometer
+/// calibration, as returned by
+/// [`collect_magnet_sample()`](crate::Bmi160::collect_magnet_sample).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct MagnetCalibrator<const N: usize> {
+    samples: [[f32; 3]; N],
+    len: usize,
+    min_distance: f32,
+}
+
+impl<const N: usize> MagnetCalibrator<N> {
+    /// Create a new calibrator rejecting samples closer than `min_distance`
+    /// µT to a stored sample.
+    pub fn new(min_distance: f32) -> Self {
+        MagnetCalibrator {
+            samples: [[0.0; 3]; N],
+            len: 0,
+            min_distance,
+        }
+    }
+
+    /// Add a sample in µT and return whether it was stored.
+    pub fn add_sample(&mut self, sample: Sensor3DDataScaled) -> bool {
+        let v = [sample.x, sample.y, sample.z];
+        let min_distance2 = self.min_distance * self.min_distance;
+        let is_new = self.samples[..self.len]
+            .iter()
+            .all(|s| distance2(s, &v) >= min_distance2);
+        if self.len == N || !is_new {
+            return false;
+        }
+        self.samples[self.len] = v;
+        self.len += 1;
+        true
+    }
+
+    /// Number of stored samples
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no samples are stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the sample buffer is full
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Discard all samples.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Fit an ellipsoid to the samples.
+    ///
+    /// The corrected field is `matrix * (data - bias)`, a sphere with the
+    /// radius of the geometric mean of the ellipsoid semi-axes.
+    /// Returns `None` if there are fewer than 9 samples or the samples do
+    /// not describe an ellipsoid.
+    pub fn fit(&self) -> Option<MagnetFit> {
+        let samples = &self.samples[..self.len];
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        // Fit around the sample mean for better conditioning.
+        let mut mean = [0.0f64; 3];
+        for s in samples {
+            for axis in 0..3 {
+                mean[axis] += f64::from(s[axis]);
+            }
+        }
+        let mean = mean.map(|m| m / samples.len() as f64);
+
+        // Least squares fit of
+        // ax² + by² + cz² + 2dxy + 2exz + 2fyz + 2gx + 2hy + 2iz = 1
+        let mut normal = [[0.0f64; 9]; 9];
+        let mut rhs = [0.0f64; 9];
+        for s in samples {
+            let [x, y, z] = [0, 1, 2].map(|i| f64::from(s[i]) - mean[i]);
+            let row = [
+                x * x,
+                y * y,
+                z * z,
+                2.0 * x * y,
+                2.0 * x * z,
+                2.0 * y * z,
+                2.0 * x,
+                2.0 * y,
+                2.0 * z,
+            ];
+            for i in 0..9 {
+                for j in 0..9 {
+                    normal[i][j] += row[i] * row[j];
+                }
+                rhs[i] += row[i];
+            }
+        }
+        let p = solve(normal, rhs)?;
+        let q = [[p[0], p[3], p[4]], [p[3], p[1], p[5]], [p[4], p[5], p[2]]];
+        let center = mul(&inverse(&q)?, &[-p[6], -p[7], -p[8]]);
+        let k = 1.0 + dot(&center, &mul(&q, &center));
+        if k <= 0.0 {
+            return None;
+        }
+        let a = q.map(|row| row.map(|v| v / k));
+
+        // The soft-iron correction is radius * A^(1/2).
+        let (eigenvalues, eigenvectors) = symmetric_eigen(a);
+        if eigenvalues.iter().any(|&e| e <= 0.0) {
+            return None;
+        }
+        let det = eigenvalues[0] * eigenvalues[1] * eigenvalues[2];
+        let radius = libm::pow(det, -1.0 / 6.0);
+        let roots = eigenvalues.map(libm::sqrt);
+        let mut matrix = [[0.0f32; 3]; 3];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                let sum: f64 = (0..3)
+                    .map(|n| eigenvectors[i][n] * roots[n] * eigenvectors[j][n])
+                    .sum();
+                *value = (radius * sum) as f32;
+            }
+        }
+        let calibration = SensorCalibration {
+            bias: [0, 1, 2].map(|i| (center[i] + mean[i]) as f32),
+            matrix,
+        };
+        let radius = radius as f32;
+        let mut sum2 = 0.0;
+        for s in samples {
+            let c = calibration.apply(Sensor3DDataScaled {
+                x: s[0],
+                y: s[1],
+                z: s[2],
+            });
+            let deviation = libm::sqrtf(c.x * c.x + c.y * c.y + c.z * c.z) - radius;
+            sum2 += deviation * deviation;
+        }
+        Some(MagnetFit {
+            calibration,
+            field_strength: radius,
+            residual: libm::sqrtf(sum2 / samples.len() as f32) / radius,
+        })
+    }
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Read the magnetometer and add the sample to the calibrator.
+    ///
+    /// The sample is read in µT in the sensor axes, ignoring the axis
+    /// remapping and the magnetometer calibration. Returns whether the sample
+    /// was stored.
+    ///
+    /// Returns `Error::InvalidInputData` if no magnetometer was set up with
+    /// [`setup_magnetometer()`](Self::setup_magnetometer).
+    pub fn collect_magnet_sample<const N: usize>(
+        &mut self,
+        calibrator: &mut MagnetCalibrator<N>,
+    ) -> Result<bool, Error<CommE>> {
+        let conversion = self.magnet_conversion.ok_or(Error::InvalidInputData)?;
+        let data = self.sensor_data(SensorSelector::new().magnet())?;
+        match data.magnet {
+            Some(d) => Ok(calibrator.add_sample(conversion.convert(&d))),
+            None => Ok(false),
+        }
+    }
+}
+
+fn distance2(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn mul(m: &[[f64; 3]; 3], v: &[f64; 3]) -> [f64; 3] {
+    m.map(|row| dot(&row, v))
+}
+
+fn inverse(m: &[[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let cofactor = |i: usize, j: usize| {
+        let (r0, r1) = ((i + 1) % 3, (i + 2) % 3);
+        let (c0, c1) = ((j + 1) % 3, (j + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    let det = m[0][0] * cofactor(0, 0) + m[0][1] * cofactor(0, 1) + m[0][2] * cofactor(0, 2);
+    if det == 0.0 {
+        return None;
+    }
+    // The inverse is the transposed cofactor matrix divided by the determinant.
+    Some([0, 1, 2].map(|i| [0, 1, 2].map(|j| cofactor(j, i) / det)))
+}
+
+/// Solve a linear system with Gaussian elimination and partial pivoting.
+fn solve(mut m: [[f64; 9]; 9], mut b: [f64; 9]) -> Option<[f64; 9]> {
+    for col in 0..9 {
+        let pivot =
+            (col..9).max_by(|&i, &j| libm::fabs(m[i][col]).total_cmp(&libm::fabs(m[j][col])))?;
+        if libm::fabs(m[pivot][col]) < 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = m[col];
+        for row in col + 1..9 {
+            let factor = m[row][col] / pivot_row[col];
+            for (value, pivot_value) in m[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = [0.0; 9];
+    for row in (0..9).rev() {
+        let sum: f64 = (row + 1..9).map(|k| m[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / m[row][row];
+    }
+    Some(x)
+}
+
+/// Eigenvalues and eigenvectors (as columns) of a symmetric matrix with the
+/// Jacobi eigenvalue algorithm.
+fn symmetric_eigen(mut a: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..JACOBI_SWEEPS {
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if a[p][q] == 0.0 {
+                continue;
+            }
+            let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+            let t =
+                libm::copysign(1.0, theta) / (libm::fabs(theta) + libm::sqrt(theta * theta + 1.0));
+            let c = 1.0 / libm::sqrt(t * t + 1.0);
+            let s = t * c;
+            for row in a.iter_mut() {
+                let (akp, akq) = (row[p], row[q]);
+                row[p] = c * akp - s * akq;
+                row[q] = s * akp + c * akq;
+            }
+            let (ap, aq) = (a[p], a[q]);
+            a[p] = [0, 1, 2].map(|k| c * ap[k] - s * aq[k]);
+            a[q] = [0, 1, 2].map(|k| s * ap[k] + c * aq[k]);
+            for row in v.iter_mut() {
+                let (vkp, vkq) = (row[p], row[q]);
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Points on a unit sphere
+    fn sphere_points() -> [[f32; 3]; 26] {
+        let mut points = [[0.0; 3]; 26];
+        let mut n = 0;
+        for x in -1i8..=1 {
+            for y in -1i8..=1 {
+                for z in -1i8..=1 {
+                    if (x, y, z) == (0, 0, 0) {
+                        continue;
+                    }
+                    let v = [x, y, z].map(f32::from);
+                    let norm = libm::sqrtf(v[0] * v[0] + v[1] * v[1] + v[2] * v[2]);
+                    points[n] = v.map(|c| c / norm);
+                    n += 1;
+                }
+            }
+        }
+        points
+    }
+
+    fn distorted(p: [f32; 3]) -> Sensor3DDataScaled {
+        // Soft iron: scale and cross-coupling, hard iron: offset
+        let soft = [[60.0, 5.0, 0.0], [5.0, 45.0, 0.0], [0.0, 0.0, 50.0]];
+        let offset = [20.0, -35.0, 10.0];
+        let [x, y, z] = [0, 1, 2]
+            .map(|i| soft[i][0] * p[0] + soft[i][1] * p[1] + soft[i][2] * p[2] + offset[i]);
+        Sensor3DDataScaled { x, y, z }
+    }
+
+    #[test]
+    fn can_fit_ellipsoid() {
+        let mut calibrator = MagnetCalibrator::<32>::new(1.0);
+        for p in sphere_points() {
+            assert!(calibrator.add_sample(distorted(p)));
+        }
+        let fit = calibrator.fit().unwrap();
+        let bias = fit.calibration.bias;
+        assert!((bias[0] - 20.0).abs() < 1e-3);
+        assert!((bias[1] + 35.0).abs() < 1e-3);
+        assert!((bias[2] - 10.0).abs() < 1e-3);
+        assert!(fit.residual < 1e-4);
+        for p in sphere_points() {
+            let c = fit.calibration.apply(distorted(p));
+            let norm = libm::sqrtf(c.x * c.x + c.y * c.y + c.z * c.z);
+            assert!((norm - fit.field_strength).abs() < 1e-2);
+        }
+        // The correction matrix is symmetric.
+        let m = fit.calibration.matrix;
+        assert!((m[0][1] - m[1][0]).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reports_noise_in_residual() {
+        let mut calibrator = MagnetCalibrator::<32>::new(1.0);
+        for (i, p) in sphere_points().into_iter().enumerate() {
+            let noise = if i % 2 == 0 { 1.02 } else { 0.98 };
+            calibrator.add_sample(distorted(p.map(|c| c * noise)));
+        }
+        let fit = calibrator.fit().unwrap();
+        assert!(fit.residual > 0.005 && fit.residual < 0.05);
+    }
+
+    #[test]
+    fn rejects_close_samples_and_full_buffer() {
+        let mut calibrator = MagnetCalibrator::<2>::new(5.0);
+        let sample = |x| Sensor3DDataScaled { x, y: 0.0, z: 0.0 };
+        assert!(calibrator.add_sample(sample(0.0)));
+        assert!(!calibrator.add_sample(sample(4.0)));
+        assert!(calibrator.add_sample(sample(6.0)));
+        assert!(calibrator.is_full());
+        assert!(!calibrator.add_sample(sample(20.0)));
+        assert_eq!(calibrator.len(), 2);
+        calibrator.clear();
+        assert!(calibrator.is_empty());
+    }
+
+    #[test]
+    fn cannot_fit_few_or_degenerate_samples() {
+        let mut calibrator = MagnetCalibrator::<32>::new(0.1);
+        for i in 0..8 {
+            calibrator.add_sample(distorted(sphere_points()[i]));
+        }
+        assert_eq!(calibrator.fit(), None);
+        calibrator.clear();
+        // All samples on a plane
+        for i in 0..20 {
+            let x = i as f32;
+            calibrator.add_sample(Sensor3DDataScaled {
+                x,
+                y: 2.0 * x,
+                z: 0.0,
+            });
+        }
+        assert_eq!(calibrator.fit(), None);
+    }
+}
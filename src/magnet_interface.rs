@@ -0,0 +1,216 @@
+use crate::{
+    device_impl::magnet_power_mode_cmd,
+    interface::{ReadData, WriteData},
+    magnetometer::{AuxMagnetometer, Bmm150, Conversion, InitStep, MAX_CALIBRATION_LEN},
+    sequence::Op,
+    BitFlags, Bmi160, Bmm150Preset, Error, MagnetometerConfig, MagnetometerPowerMode, Register,
+};
+use embedded_hal::delay::DelayNs;
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Enable the secondary magnetometer interface.
+    ///
+    /// This configures the interface mode as primary interface plus
+    /// secondary magnetometer interface. The magnetometer interface must
+    /// additionally be powered with [`set_magnet_power_mode()`](Self::set_magnet_power_mode).
+    pub fn enable_magnet_interface(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_interface_op()], &mut [])
+    }
+
+    /// Set the 7-bit I2C address of the device connected to the
+    /// secondary magnetometer interface.
+    ///
+    /// Returns `Error::InvalidInputData` if the address does not fit in 7 bits.
+    pub fn set_magnet_interface_address(&mut self, address: u8) -> Result<(), Error<CommE>> {
+        self.run([magnet_address_op(address)?], &mut [])
+    }
+
+    /// Enable magnetometer interface manual mode (setup mode).
+    ///
+    /// In this mode the registers of the secondary device can be accessed
+    /// with [`read_magnet_register()`](Self::read_magnet_register) and
+    /// [`write_magnet_register()`](Self::write_magnet_register).
+    pub fn enable_magnet_manual_mode(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_manual_mode_op(true)], &mut [])
+    }
+
+    /// Disable magnetometer interface manual mode (switch to data mode).
+    pub fn disable_magnet_manual_mode(&mut self) -> Result<(), Error<CommE>> {
+        self.run([magnet_manual_mode_op(false)], &mut [])
+    }
+
+    /// Set the magnetometer output data rate, read burst length and
+    /// trigger-readout offset used in data mode.
+    ///
+    /// Returns `Error::InvalidInputData` if the offset does not fit in 4 bits
+    /// or if it is not shorter than the output data rate period.
+    pub fn set_magnet_config(&mut self, config: MagnetometerConfig) -> Result<(), Error<CommE>> {
+        self.run(magnet_config_ops(&config)?, &mut [])
+    }
+
+    /// Read a register of the device connected to the secondary
+    /// magnetometer interface.
+    ///
+    /// Manual mode must be enabled. This waits until the manual operation
+    /// is finished and returns `Error::Timeout` if it does not finish.
+    pub fn read_magnet_register(&mut self, register: u8) -> Result<u8, Error<CommE>> {
+        let mut value = [0];
+        self.run([Op::ReadMagnet(register)], &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Write a register of the device connected to the secondary
+    /// magnetometer interface.
+    ///
+    /// Manual mode must be enabled. This waits until the manual operation
+    /// is finished and returns `Error::Timeout` if it does not finish.
+    pub fn write_magnet_register(&mut self, register: u8, data: u8) -> Result<(), Error<CommE>> {
+        self.run([Op::WriteMagnet(register, data)], &mut [])
+    }
+
+    /// Set up a magnetometer connected to the secondary interface.
+    ///
+    /// This powers the magnetometer interface, initializes the magnetometer,
+    /// reads its calibration data, configures the measurement trigger, data
+    /// register and output data rate and switches the interface to data
+    /// mode. Afterwards, the magnetometer data is available through
+    /// [`data()`](Self::data) and converted to µT through
+    /// [`data_scaled()`](Self::data_scaled) when selecting the magnetometer.
+    ///
+    /// Returns `Error::InvalidInputData` if the magnetometer requests more
+    /// than [`MAX_CALIBRATION_LEN`] calibration bytes and `Error::Timeout`
+    /// if a register access on the secondary interface does not finish.
+    pub fn setup_magnetometer<M: AuxMagnetometer, D: DelayNs>(
+        &mut self,
+        magnetometer: &M,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        let mut calibration = [0; MAX_CALIBRATION_LEN];
+        let (ops, calibration_len) = magnet_setup_ops(magnetometer)?;
+        self.run_delayed(ops, delay, &mut calibration)?;
+        self.magnet_conversion = Some(Conversion::new::<M>(&calibration[..calibration_len]));
+        Ok(())
+    }
+
+    /// Set up a BMM150 magnetometer connected to the secondary interface.
+    ///
+    /// This is equivalent to calling [`setup_magnetometer()`](Self::setup_magnetometer)
+    /// with a [`Bmm150`] using the given preset.
+    pub fn setup_bmm150<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        preset: Bmm150Preset,
+    ) -> Result<(), Error<CommE>> {
+        self.setup_magnetometer(&Bmm150::new(preset), delay)
+    }
+}
+
+/// Register operations done by `setup_magnetometer()` and the number of
+/// calibration bytes they read
+pub(crate) fn magnet_setup_ops<M: AuxMagnetometer, E>(
+    magnetometer: &M,
+) -> Result<(impl Iterator<Item = Op> + '_, usize), Error<E>> {
+    let (calibration_register, calibration_len) = calibration_registers(magnetometer)?;
+    let setup = [
+        Op::Write(
+            Register::CMD,
+            magnet_power_mode_cmd(MagnetometerPowerMode::Normal),
+        ),
+        Op::DelayUs(650),
+        magnet_interface_op(),
+        magnet_address_op(magnetometer.address())?,
+        magnet_manual_mode_op(true),
+    ];
+    let init = magnetometer.init_steps().iter().map(|step| match *step {
+        InitStep::Write { register, value } => Op::WriteMagnet(register, value),
+        InitStep::DelayUs(us) => Op::DelayUs(us),
+    });
+    let calibration =
+        (0..calibration_len).map(move |i| Op::ReadMagnet(calibration_register + i as u8));
+    // The write and read addresses are kept for data mode, where the
+    // BMI160 triggers a measurement and then reads the data registers
+    // at the configured output data rate.
+    let (trigger_register, trigger_value) = magnetometer.trigger();
+    let [magnet_conf, mag_if_1] = magnet_config_ops(&data_mode_config(magnetometer))?;
+    let data_mode = [
+        Op::WriteMagnet(trigger_register, trigger_value),
+        Op::Write(Register::MAG_IF_2, magnetometer.data_register()),
+        Op::WaitMagnet,
+        magnet_conf,
+        mag_if_1,
+        magnet_manual_mode_op(false),
+    ];
+    let ops = setup
+        .into_iter()
+        .chain(init)
+        .chain(calibration)
+        .chain(data_mode);
+    Ok((ops, calibration_len))
+}
+
+pub(crate) fn magnet_interface_op() -> Op {
+    Op::update(Register::IF_CONF, if_conf_with_magnet)
+}
+
+pub(crate) fn magnet_address_op<E>(address: u8) -> Result<Op, Error<E>> {
+    if address > 0x7F {
+        return Err(Error::InvalidInputData);
+    }
+    Ok(Op::Write(Register::MAG_IF_0, address << 1))
+}
+
+pub(crate) fn magnet_manual_mode_op(enabled: bool) -> Op {
+    if enabled {
+        Op::update(Register::MAG_IF_1, |v| v | BitFlags::MAG_MANUAL_EN)
+    } else {
+        Op::update(Register::MAG_IF_1, |v| v & !BitFlags::MAG_MANUAL_EN)
+    }
+}
+
+pub(crate) fn magnet_config_ops<E>(config: &MagnetometerConfig) -> Result<[Op; 2], Error<E>> {
+    check_magnet_config(config)?;
+    Ok([
+        Op::Write(Register::MAG_CONF, config.odr as u8),
+        Op::update(Register::MAG_IF_1, |v| mag_if_1_with_config(v, config)),
+    ])
+}
+
+pub(crate) fn if_conf_with_magnet(conf: u8) -> u8 {
+    (conf & !BitFlags::IF_MODE) | BitFlags::IF_MODE_MAG
+}
+
+pub(crate) fn check_magnet_config<CommE>(config: &MagnetometerConfig) -> Result<(), Error<CommE>> {
+    if config.offset > 15 || u32::from(config.offset) * 2500 >= config.odr.period_us() {
+        Err(Error::InvalidInputData)
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn mag_if_1_with_config(value: u8, config: &MagnetometerConfig) -> u8 {
+    (value & BitFlags::MAG_MANUAL_EN)
+        | ((config.offset << 2) & BitFlags::MAG_OFFSET)
+        | (config.read_burst as u8 & BitFlags::MAG_RD_BURST)
+}
+
+pub(crate) fn calibration_registers<M: AuxMagnetometer, CommE>(
+    magnetometer: &M,
+) -> Result<(u8, usize), Error<CommE>> {
+    let (register, len) = magnetometer.calibration_registers().unwrap_or((0, 0));
+    if len > MAX_CALIBRATION_LEN {
+        Err(Error::InvalidInputData)
+    } else {
+        Ok((register, len))
+    }
+}
+
+pub(crate) fn data_mode_config<M: AuxMagnetometer>(magnetometer: &M) -> MagnetometerConfig {
+    MagnetometerConfig {
+        odr: magnetometer.odr(),
+        read_burst: magnetometer.read_burst(),
+        offset: 0,
+    }
+}
@@ -0,0 +1,136 @@
+use super::{AuxMagnetometer, InitStep};
+use crate::{MagnetometerData, MagnetometerOdr, Sensor3DDataScaled};
+
+const AK09916_ADDR: u8 = 0x0C;
+
+struct Ak09916Register;
+impl Ak09916Register {
+    const HXL: u8 = 0x11;
+    const CNTL2: u8 = 0x31;
+    const CNTL3: u8 = 0x32;
+}
+
+const POWER_DOWN: u8 = 0x00;
+const SINGLE_MEASUREMENT: u8 = 0x01;
+const SOFT_RESET: u8 = 0x01;
+/// HOFL flag of the ST2 register, read as the last data byte.
+const OVERFLOW: u16 = 1 << (3 + 8);
+/// µT per LSB
+const SENSITIVITY: f32 = 0.15;
+
+/// AKM AK09916 magnetometer
+///
+/// The magnetometer is operated in single measurement mode, triggered by
+/// the BMI160 at the configured output data rate. The data registers are
+/// read from HXL up to ST2 so that the data is released after each readout.
+///
+/// Note that the AK09916 axes are not necessarily aligned with the BMI160
+/// axes. This depends on how both devices are mounted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ak09916 {
+    odr: MagnetometerOdr,
+}
+
+impl Ak09916 {
+    /// Create a new AK09916 read at the given output data rate.
+    ///
+    /// A single measurement takes up to 8.2 ms so the output data rate
+    /// should not exceed 100 Hz.
+    pub fn new(odr: MagnetometerOdr) -> Self {
+        Ak09916 { odr }
+    }
+}
+
+impl Default for Ak09916 {
+    fn default() -> Self {
+        Ak09916::new(MagnetometerOdr::Hz50)
+    }
+}
+
+const INIT_STEPS: [InitStep; 3] = [
+    InitStep::Write {
+        register: Ak09916Register::CNTL3,
+        value: SOFT_RESET,
+    },
+    InitStep::DelayUs(100),
+    InitStep::Write {
+        register: Ak09916Register::CNTL2,
+        value: POWER_DOWN,
+    },
+];
+
+impl AuxMagnetometer for Ak09916 {
+    fn address(&self) -> u8 {
+        AK09916_ADDR
+    }
+
+    fn init_steps(&self) -> &[InitStep] {
+        &INIT_STEPS
+    }
+
+    fn trigger(&self) -> (u8, u8) {
+        (Ak09916Register::CNTL2, SINGLE_MEASUREMENT)
+    }
+
+    fn data_register(&self) -> u8 {
+        Ak09916Register::HXL
+    }
+
+    fn odr(&self) -> MagnetometerOdr {
+        self.odr
+    }
+
+    fn convert(_calibration: &[u8], data: &MagnetometerData) -> Sensor3DDataScaled {
+        if (data.hall_resistence & OVERFLOW) != 0 {
+            return Sensor3DDataScaled {
+                x: f32::NAN,
+                y: f32::NAN,
+                z: f32::NAN,
+            };
+        }
+        Sensor3DDataScaled {
+            x: f32::from(data.axes.x) * SENSITIVITY,
+            y: f32::from(data.axes.y) * SENSITIVITY,
+            z: f32::from(data.axes.z) * SENSITIVITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sensor3DData;
+
+    #[test]
+    fn can_convert() {
+        let data = MagnetometerData {
+            axes: Sensor3DData {
+                x: 100,
+                y: -200,
+                z: 300,
+            },
+            hall_resistence: 0,
+        };
+        let result = Ak09916::convert(&[], &data);
+        assert_eq!(
+            result,
+            Sensor3DDataScaled {
+                x: 100. * 0.15,
+                y: -200. * 0.15,
+                z: 300. * 0.15,
+            }
+        );
+    }
+
+    #[test]
+    fn overflow_is_nan() {
+        let data = MagnetometerData {
+            axes: Sensor3DData { x: 1, y: 2, z: 3 },
+            hall_resistence: 0b0000_1000_0000_0000,
+        };
+        let result = Ak09916::convert(&[], &data);
+        assert!(result.x.is_nan());
+        assert!(result.y.is_nan());
+        assert!(result.z.is_nan());
+    }
+}
@@ -0,0 +1,269 @@
+use super::{AuxMagnetometer, InitStep};
+use crate::{Bmm150Preset, MagnetometerData, MagnetometerOdr, Sensor3DDataScaled};
+
+const BMM150_ADDR: u8 = 0x10;
+
+struct Bmm150Register;
+impl Bmm150Register {
+    const DATA_X_LSB: u8 = 0x42;
+    const POWER_CONTROL: u8 = 0x4B;
+    const OP_MODE: u8 = 0x4C;
+    const REP_XY: u8 = 0x51;
+    const REP_Z: u8 = 0x52;
+    const DIG_X1: u8 = 0x5D;
+}
+
+const POWER_ON: u8 = 0x01;
+const FORCED_MODE: u8 = 0x02;
+/// Trim registers from DIG_X1 (0x5D) to DIG_XY1 (0x71)
+const TRIM_LEN: usize = 21;
+
+const OVERFLOW_ADCVAL_XY: i16 = -4096;
+const OVERFLOW_ADCVAL_Z: i16 = -16384;
+
+impl Bmm150Preset {
+    /// XY and Z repetition register values.
+    fn repetitions(self) -> (u8, u8) {
+        match self {
+            Bmm150Preset::LowPower => (0x01, 0x02),
+            Bmm150Preset::Regular => (0x04, 0x0E),
+            Bmm150Preset::EnhancedRegular => (0x07, 0x1A),
+            Bmm150Preset::HighAccuracy => (0x17, 0x52),
+        }
+    }
+
+    /// Output data rate closest to the recommended preset ODR.
+    fn odr(self) -> MagnetometerOdr {
+        match self {
+            Bmm150Preset::HighAccuracy => MagnetometerOdr::Hz25,
+            _ => MagnetometerOdr::Hz12_5,
+        }
+    }
+}
+
+/// Bosch BMM150 magnetometer
+///
+/// The magnetometer is operated in forced mode, triggered by the BMI160 at
+/// the output data rate recommended for the selected preset. The data is
+/// compensated with the factory trim registers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bmm150 {
+    preset: Bmm150Preset,
+    init_steps: [InitStep; 4],
+}
+
+impl Bmm150 {
+    /// Create a new BMM150 with the given repetitions preset.
+    pub fn new(preset: Bmm150Preset) -> Self {
+        let (rep_xy, rep_z) = preset.repetitions();
+        Bmm150 {
+            preset,
+            init_steps: [
+                InitStep::Write {
+                    register: Bmm150Register::POWER_CONTROL,
+                    value: POWER_ON,
+                },
+                InitStep::DelayUs(3000),
+                InitStep::Write {
+                    register: Bmm150Register::REP_XY,
+                    value: rep_xy,
+                },
+                InitStep::Write {
+                    register: Bmm150Register::REP_Z,
+                    value: rep_z,
+                },
+            ],
+        }
+    }
+}
+
+impl Default for Bmm150 {
+    fn default() -> Self {
+        Bmm150::new(Bmm150Preset::default())
+    }
+}
+
+impl AuxMagnetometer for Bmm150 {
+    fn address(&self) -> u8 {
+        BMM150_ADDR
+    }
+
+    fn init_steps(&self) -> &[InitStep] {
+        &self.init_steps
+    }
+
+    fn calibration_registers(&self) -> Option<(u8, usize)> {
+        Some((Bmm150Register::DIG_X1, TRIM_LEN))
+    }
+
+    fn trigger(&self) -> (u8, u8) {
+        (Bmm150Register::OP_MODE, FORCED_MODE)
+    }
+
+    fn data_register(&self) -> u8 {
+        Bmm150Register::DATA_X_LSB
+    }
+
+    fn odr(&self) -> MagnetometerOdr {
+        self.preset.odr()
+    }
+
+    fn convert(calibration: &[u8], data: &MagnetometerData) -> Sensor3DDataScaled {
+        Bmm150TrimData::new(calibration).compensate(data)
+    }
+}
+
+/// BMM150 factory trim data
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Bmm150TrimData {
+    dig_x1: i8,
+    dig_y1: i8,
+    dig_x2: i8,
+    dig_y2: i8,
+    dig_z1: u16,
+    dig_z2: i16,
+    dig_z3: i16,
+    dig_z4: i16,
+    dig_xy1: u8,
+    dig_xy2: i8,
+    dig_xyz1: u16,
+}
+
+impl Bmm150TrimData {
+    /// Decode the trim registers from DIG_X1 (0x5D) to DIG_XY1 (0x71).
+    fn new(trim: &[u8]) -> Self {
+        Bmm150TrimData {
+            dig_x1: trim[0] as i8,
+            dig_y1: trim[1] as i8,
+            dig_z4: i16::from_le_bytes([trim[5], trim[6]]),
+            dig_x2: trim[7] as i8,
+            dig_y2: trim[8] as i8,
+            dig_z2: i16::from_le_bytes([trim[11], trim[12]]),
+            dig_z1: u16::from_le_bytes([trim[13], trim[14]]),
+            dig_xyz1: u16::from_le_bytes([trim[15], trim[16] & 0x7F]),
+            dig_z3: i16::from_le_bytes([trim[17], trim[18]]),
+            dig_xy2: trim[19] as i8,
+            dig_xy1: trim[20],
+        }
+    }
+
+    /// Compensate the raw BMM150 data registers and return the field in µT.
+    ///
+    /// Axes whose measurement overflowed are returned as `NaN`.
+    fn compensate(&self, data: &MagnetometerData) -> Sensor3DDataScaled {
+        // The data registers contain 13-bit X/Y, 15-bit Z and 14-bit
+        // hall resistance values aligned to the MSB.
+        let rhall = data.hall_resistence >> 2;
+        Sensor3DDataScaled {
+            x: self.compensate_xy(data.axes.x >> 3, rhall, self.dig_x1, self.dig_x2),
+            y: self.compensate_xy(data.axes.y >> 3, rhall, self.dig_y1, self.dig_y2),
+            z: self.compensate_z(data.axes.z >> 1, rhall),
+        }
+    }
+
+    fn compensate_xy(&self, raw: i16, rhall: u16, dig_1: i8, dig_2: i8) -> f32 {
+        if raw == OVERFLOW_ADCVAL_XY || rhall == 0 || self.dig_xyz1 == 0 {
+            return f32::NAN;
+        }
+        let rhall_comp = f32::from(self.dig_xyz1) * 16384.0 / f32::from(rhall) - 16384.0;
+        let xy1 = f32::from(self.dig_xy2) * (rhall_comp * rhall_comp / 268_435_456.0);
+        let xy2 = xy1 + rhall_comp * f32::from(self.dig_xy1) / 16384.0;
+        let xy3 = f32::from(dig_2) + 160.0;
+        let xy4 = f32::from(raw) * ((xy2 + 256.0) * xy3);
+        ((xy4 / 8192.0) + f32::from(dig_1) * 8.0) / 16.0
+    }
+
+    fn compensate_z(&self, raw: i16, rhall: u16) -> f32 {
+        if raw == OVERFLOW_ADCVAL_Z
+            || self.dig_z1 == 0
+            || self.dig_z2 == 0
+            || self.dig_xyz1 == 0
+            || rhall == 0
+        {
+            return f32::NAN;
+        }
+        let z0 = f32::from(raw) - f32::from(self.dig_z4);
+        let z1 = f32::from(rhall) - f32::from(self.dig_xyz1);
+        let z2 = f32::from(self.dig_z3) * z1;
+        let z3 = f32::from(self.dig_z1) * f32::from(rhall) / 32768.0;
+        let z4 = f32::from(self.dig_z2) + z3;
+        let z5 = z0 * 131_072.0 - z2;
+        (z5 / (z4 * 4.0)) / 16.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sensor3DData;
+
+    const TRIM: Bmm150TrimData = Bmm150TrimData {
+        dig_x1: 0,
+        dig_y1: 0,
+        dig_x2: 26,
+        dig_y2: 26,
+        dig_z1: 24747,
+        dig_z2: 763,
+        dig_z3: 0,
+        dig_z4: 0,
+        dig_xy1: 29,
+        dig_xy2: -3,
+        dig_xyz1: 6570,
+    };
+
+    fn raw(x: i16, y: i16, z: i16, rhall: u16) -> MagnetometerData {
+        MagnetometerData {
+            axes: Sensor3DData {
+                x: x << 3,
+                y: y << 3,
+                z: z << 1,
+            },
+            hall_resistence: rhall << 2,
+        }
+    }
+
+    fn assert_near(expected: f32, actual: f32) {
+        assert!((expected - actual).abs() < 0.001, "{expected} != {actual}");
+    }
+
+    #[test]
+    fn can_decode_trim_registers() {
+        let trim = [
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 26, 26, 0x00, 0x00, 0xFB, 0x02, 0xAB, 0x60,
+            0xAA, 0x99, 0x00, 0x00, 0xFD, 0x1D,
+        ];
+        assert_eq!(TRIM, Bmm150TrimData::new(&trim));
+    }
+
+    #[test]
+    fn can_compensate() {
+        let result = TRIM.compensate(&raw(120, -200, 300, 6500));
+        assert_near(43.646_873, result.x);
+        assert_near(-72.744_79, result.y);
+        assert_near(108.323_1, result.z);
+    }
+
+    #[test]
+    fn xy_overflow_is_nan() {
+        let result = TRIM.compensate(&raw(-4096, -4096, 300, 6500));
+        assert!(result.x.is_nan());
+        assert!(result.y.is_nan());
+        assert!(!result.z.is_nan());
+    }
+
+    #[test]
+    fn z_overflow_is_nan() {
+        let result = TRIM.compensate(&raw(120, -200, -16384, 6500));
+        assert!(!result.x.is_nan());
+        assert!(!result.y.is_nan());
+        assert!(result.z.is_nan());
+    }
+
+    #[test]
+    fn zero_hall_resistance_is_nan() {
+        let result = TRIM.compensate(&raw(120, -200, 300, 0));
+        assert!(result.x.is_nan());
+        assert!(result.y.is_nan());
+        assert!(result.z.is_nan());
+    }
+}
@@ -0,0 +1,107 @@
+//! Magnetometers connected to the secondary magnetometer interface
+//!
+//! The BMI160 can read an external magnetometer through its secondary I2C
+//! interface. The [`AuxMagnetometer`] trait describes how such a device is
+//! initialized, triggered and read, and how its data is converted to µT.
+//! Any implementation can be set up with
+//! [`Bmi160::setup_magnetometer()`](crate::Bmi160::setup_magnetometer).
+//!
+//! Implementations are provided for the Bosch [`Bmm150`] and the AKM
+//! [`Ak09916`].
+
+use crate::{MagnetometerData, MagnetometerOdr, MagnetometerReadBurst, Sensor3DDataScaled};
+use core::fmt;
+
+mod ak09916;
+mod bmm150;
+pub use self::ak09916::Ak09916;
+pub use self::bmm150::Bmm150;
+
+/// Maximum number of calibration bytes that can be read from a magnetometer.
+pub const MAX_CALIBRATION_LEN: usize = 24;
+
+/// Magnetometer initialization step
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum InitStep {
+    /// Write a value to a magnetometer register
+    Write {
+        /// Register address
+        register: u8,
+        /// Value
+        value: u8,
+    },
+    /// Wait for the given number of microseconds
+    DelayUs(u32),
+}
+
+/// Magnetometer connected to the secondary magnetometer interface
+pub trait AuxMagnetometer {
+    /// 7-bit I2C address of the magnetometer
+    fn address(&self) -> u8;
+
+    /// Steps performed in setup mode to initialize the magnetometer
+    fn init_steps(&self) -> &[InitStep];
+
+    /// First register and number of calibration bytes to read after
+    /// initialization (at most [`MAX_CALIBRATION_LEN`]).
+    ///
+    /// The calibration data is passed to [`convert()`](Self::convert).
+    fn calibration_registers(&self) -> Option<(u8, usize)> {
+        None
+    }
+
+    /// Register write which triggers a measurement before each readout in
+    /// data mode, as `(register, value)`.
+    fn trigger(&self) -> (u8, u8);
+
+    /// First data register read in data mode
+    fn data_register(&self) -> u8;
+
+    /// Number of bytes read in data mode
+    fn read_burst(&self) -> MagnetometerReadBurst {
+        MagnetometerReadBurst::Bytes8
+    }
+
+    /// Output data rate used in data mode
+    fn odr(&self) -> MagnetometerOdr;
+
+    /// Convert the data read from the magnetometer to µT.
+    ///
+    /// The axes in `data` hold the first 6 bytes read as little endian
+    /// values and `hall_resistence` holds the last 2 bytes.
+    fn convert(calibration: &[u8], data: &MagnetometerData) -> Sensor3DDataScaled;
+}
+
+/// Conversion of the raw magnetometer data stored in the driver after setup
+#[derive(Clone, Copy)]
+pub(crate) struct Conversion {
+    convert: fn(&[u8], &MagnetometerData) -> Sensor3DDataScaled,
+    calibration: [u8; MAX_CALIBRATION_LEN],
+    calibration_len: usize,
+}
+
+impl Conversion {
+    pub(crate) fn new<M: AuxMagnetometer>(calibration: &[u8]) -> Self {
+        let mut conversion = Conversion {
+            convert: M::convert,
+            calibration: [0; MAX_CALIBRATION_LEN],
+            calibration_len: calibration.len(),
+        };
+        conversion.calibration[..calibration.len()].copy_from_slice(calibration);
+        conversion
+    }
+
+    pub(crate) fn convert(&self, data: &MagnetometerData) -> Sensor3DDataScaled {
+        (self.convert)(&self.calibration[..self.calibration_len], data)
+    }
+}
+
+// Deriving `Debug` for the function pointer fails on older compilers.
+impl fmt::Debug for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Conversion")
+            .field("calibration", &&self.calibration[..self.calibration_len])
+            .finish_non_exhaustive()
+    }
+}
@@ -1,64 +1,366 @@
 use crate::{
     interface::{ReadData, WriteData},
+    magnetometer::Conversion,
+    sequence::Op,
     types::{DataScaled, Sensor3DDataScaled},
-    Bmi160, Data, Error, MagnetometerData, Register, Sensor3DData, SensorSelector,
+    AccelerometerRange, AxisRemap, Bmi160, Calibration, Data, DataMilli, DataSi, Error,
+    GyroscopeRange, MagnetometerData, MetersPerSecondSquared, Microtesla, RadiansPerSecond,
+    Register, SampleReport, Sensor3DData, Sensor3DDataMilli, Sensor3DDataSi, SensorCalibration,
+    SensorSelector, SensorTime, Status,
 };
 
+/// Standard gravity in m/s²
+const STANDARD_GRAVITY: f32 = 9.80665;
+
 impl<DI, CommE> Bmi160<DI>
 where
     DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
 {
     /// Read latest sensor data
+    ///
+    /// The accelerometer and gyroscope data is transformed into the board
+    /// axes as set with [`set_axis_remap()`](Self::set_axis_remap). The raw
+    /// magnetometer data stays in the sensor axes.
     pub fn data(&mut self, selector: SensorSelector) -> Result<Data, Error<CommE>> {
-        let result = if selector != SensorSelector::new() {
-            let (begin, end) = get_data_addresses(selector);
-            let mut data = [0_u8; 24];
-            data[0] = begin;
-            let len = (1 + end - begin) as usize;
-            self.iface.read_data(&mut data[0..len])?;
-            get_data(selector, &data[1..], (begin - Register::MAG) as usize)
-        } else {
-            Data {
-                accel: None,
-                gyro: None,
-                magnet: None,
-                time: None,
-            }
-        };
-        Ok(result)
+        let raw_data = self.sensor_data(selector)?;
+        Ok(self.axis_remap.remap_data(raw_data))
     }
 
     /// Read latest sensor data and scale it using the gyroscope and accelerometer ranges
+    ///
+    /// The magnetometer data is converted to µT if a magnetometer was set up
+    /// with [`setup_magnetometer()`](Self::setup_magnetometer). Otherwise it
+    /// is not available.
     pub fn data_scaled(&mut self, selector: SensorSelector) -> Result<DataScaled, Error<CommE>> {
-        let raw_data = self.data(selector)?;
+        let raw_data = self.sensor_data(selector)?;
+        Ok(self.scale_data(raw_data))
+    }
+
+    /// Scale raw data, e.g. samples decoded with the [`fifo`](crate::fifo)
+    /// module, like [`data_scaled()`](Self::data_scaled).
+    ///
+    /// The data must be in the sensor axes as decoded, without
+    /// [`AxisRemap::remap_data()`](crate::AxisRemap::remap_data). The axis
+    /// remapping and the host-side calibration are applied.
+    pub fn scale_data(&self, data: Data) -> DataScaled {
+        scale_data(
+            data,
+            self.accel_range,
+            self.gyro_range,
+            self.magnet_conversion,
+            &self.axis_remap,
+            &self.calibration,
+        )
+    }
+
+    /// Read latest sensor data in physical units
+    ///
+    /// The accelerometer data is returned in m/s² and the gyroscope data
+    /// in rad/s. The magnetometer data is available in µT if a magnetometer
+    /// was set up with [`setup_magnetometer()`](Self::setup_magnetometer).
+    pub fn data_si(&mut self, selector: SensorSelector) -> Result<DataSi, Error<CommE>> {
+        let raw_data = self.sensor_data(selector)?;
+        Ok(scale_data_si(
+            raw_data,
+            self.accel_range,
+            self.gyro_range,
+            self.magnet_conversion,
+            &self.axis_remap,
+            &self.calibration,
+        ))
+    }
+
+    /// Read latest sensor data scaled to milli-g and milli-°/s with integer math
+    ///
+    /// The values are rounded to the nearest integer. The magnetometer data
+    /// is not available and the host-side calibration is not applied.
+    pub fn data_milli(&mut self, selector: SensorSelector) -> Result<DataMilli, Error<CommE>> {
+        let selector = SensorSelector {
+            magnet: false,
+            ..selector
+        };
+        let raw_data = self.sensor_data(selector)?;
+        Ok(scale_data_milli(
+            raw_data,
+            self.accel_range,
+            self.gyro_range,
+            &self.axis_remap,
+        ))
+    }
+
+    /// Read consecutive samples into `samples` as fast as the bus allows.
+    ///
+    /// Before each sample the [`status()`](Self::status) is polled until
+    /// the data-ready flags of all selected sensors are set. The sensor time
+    /// is always read and used to detect samples that were read twice, which
+    /// are discarded, and missed samples. `period_ticks` is the sample period
+    /// in sensor time ticks, e.g. from
+    /// [`AccelerometerOdr::period_ticks()`](crate::AccelerometerOdr::period_ticks).
+    ///
+    /// Returns early if no new sample is ready after `max_polls` status
+    /// polls.
+    ///
+    /// Returns `Error::InvalidInputData` if `period_ticks` is not a power of
+    /// two up to `1 << 23`, which are the periods of the output data rates.
+    pub fn read_samples(
+        &mut self,
+        selector: SensorSelector,
+        samples: &mut [Data],
+        period_ticks: u32,
+        max_polls: u32,
+    ) -> Result<SampleReport, Error<CommE>> {
+        check_period_ticks(period_ticks)?;
+        let selector = selector.time();
+        let mut collector = SampleCollector::new(samples, period_ticks, max_polls);
+        while collector.needs_poll() {
+            if collector.poll(&self.status()?, selector) {
+                let data = self.data(selector)?;
+                collector.add(data);
+            }
+        }
+        Ok(collector.report())
+    }
+
+    /// Read latest sensor data in the sensor axes
+    pub(crate) fn sensor_data(&mut self, selector: SensorSelector) -> Result<Data, Error<CommE>> {
+        if selector == SensorSelector::new() {
+            return Ok(EMPTY_DATA);
+        }
+        let (read, offset) = data_read(selector);
+        let mut data = [0; DATA_LEN];
+        self.run([read], &mut data)?;
+        Ok(get_data(selector, &data, offset))
+    }
+}
+
+/// Number of data and sensor time bytes
+pub(crate) const DATA_LEN: usize = 23;
+
+/// Read of the data registers of the selected sensors and the offset of
+/// the first register read from the magnetometer data
+pub(crate) fn data_read(selector: SensorSelector) -> (Op, usize) {
+    let (begin, end) = get_data_addresses(selector);
+    (
+        Op::Read(begin, usize::from(end - begin)),
+        usize::from(begin - Register::MAG),
+    )
+}
+
+/// Sample collection state of `read_samples()`
+pub(crate) struct SampleCollector<'a> {
+    samples: &'a mut [Data],
+    report: SampleReport,
+    previous: Option<u32>,
+    period_ticks: u32,
+    max_polls: u32,
+    polls: u32,
+}
+
+impl<'a> SampleCollector<'a> {
+    pub(crate) fn new(samples: &'a mut [Data], period_ticks: u32, max_polls: u32) -> Self {
+        SampleCollector {
+            samples,
+            report: SampleReport::default(),
+            previous: None,
+            period_ticks,
+            max_polls,
+            polls: 0,
+        }
+    }
+
+    /// Whether the buffer is not full and the polls for the next sample
+    /// are not exhausted
+    pub(crate) fn needs_poll(&self) -> bool {
+        self.report.count < self.samples.len() && self.polls < self.max_polls
+    }
+
+    /// Count a status poll and return whether the selected data is ready.
+    pub(crate) fn poll(&mut self, status: &Status, selector: SensorSelector) -> bool {
+        self.polls += 1;
+        is_data_ready(status, selector)
+    }
+
+    /// Add the data read after a successful poll.
+    pub(crate) fn add(&mut self, data: Data) {
+        self.polls = 0;
+        let report = &mut self.report;
+        if track_sample(report, &mut self.previous, data.time, self.period_ticks) {
+            self.samples[report.count] = data;
+            report.count += 1;
+        }
+    }
+
+    pub(crate) fn report(&self) -> SampleReport {
+        self.report
+    }
+}
+
+/// Whether the data-ready flags of all selected sensors are set
+pub(crate) fn is_data_ready(status: &Status, selector: SensorSelector) -> bool {
+    (!selector.accel || status.accel_data_ready)
+        && (!selector.gyro || status.gyro_data_ready)
+        && (!selector.magnet || status.magnet_data_ready)
+}
+
+/// Check that a sample period is a power of two up to `1 << 23` ticks.
+///
+/// The sensor time wraps around at `1 << 24`, so only these periods divide it
+/// and keep the grid of sample times across the wrap-around.
+pub(crate) fn check_period_ticks<E>(period_ticks: u32) -> Result<(), Error<E>> {
+    if period_ticks.is_power_of_two() && period_ticks <= 1 << 23 {
+        Ok(())
+    } else {
+        Err(Error::InvalidInputData)
+    }
+}
+
+/// Count the duplicated and missed samples since the previous sample time
+/// and return whether the sample is new.
+///
+/// `period_ticks` must have passed `check_period_ticks()`.
+pub(crate) fn track_sample(
+    report: &mut SampleReport,
+    previous: &mut Option<u32>,
+    time: Option<u32>,
+    period_ticks: u32,
+) -> bool {
+    // Samples are taken on a grid of the period in the sensor time.
+    let slot = match time {
+        Some(t) => SensorTime(t - t % period_ticks),
+        None => return true,
+    };
+    let slots = match previous.replace(slot.0) {
+        Some(p) => slot.wrapping_sub(SensorTime(p)) / period_ticks,
+        None => 1,
+    };
+    if slots == 0 {
+        report.duplicates += 1;
+        return false;
+    }
+    report.missed += slots - 1;
+    true
+}
 
-        let accel_multiplier = self.accel_range.multiplier();
-        let gyro_multiplier = self.gyro_range.multiplier();
+pub(crate) const EMPTY_DATA: Data = Data {
+    accel: None,
+    gyro: None,
+    magnet: None,
+    time: None,
+};
+
+pub(crate) fn scale_data(
+    raw_data: Data,
+    accel_range: AccelerometerRange,
+    gyro_range: GyroscopeRange,
+    magnet_conversion: Option<Conversion>,
+    remap: &AxisRemap,
+    calibration: &Calibration,
+) -> DataScaled {
+    let accel_multiplier = accel_range.multiplier();
+    let gyro_multiplier = gyro_range.multiplier();
 
-        Ok(DataScaled {
-            accel: raw_data.accel.map(|d| Sensor3DDataScaled {
+    // Calibrate in the sensor axes, then remap to the board axes.
+    let process = |d: Sensor3DDataScaled, cal: &SensorCalibration| remap.remap_scaled(cal.apply(d));
+    DataScaled {
+        accel: raw_data.accel.map(|d| {
+            let d = Sensor3DDataScaled {
                 x: d.x as f32 * accel_multiplier,
                 y: d.y as f32 * accel_multiplier,
                 z: d.z as f32 * accel_multiplier,
-            }),
-            gyro: raw_data.gyro.map(|d| Sensor3DDataScaled {
+            };
+            process(d, &calibration.accel)
+        }),
+        gyro: raw_data.gyro.map(|d| {
+            let d = Sensor3DDataScaled {
                 x: d.x as f32 * gyro_multiplier,
                 y: d.y as f32 * gyro_multiplier,
                 z: d.z as f32 * gyro_multiplier,
-            }),
-            magnet: raw_data.magnet,
-            time: raw_data.time,
-        })
+            };
+            process(d, &calibration.gyro)
+        }),
+        magnet: raw_data
+            .magnet
+            .and_then(|d| magnet_conversion.map(|c| c.convert(&d)))
+            .map(|d| process(d, &calibration.magnet)),
+        time: raw_data.time,
     }
 }
 
-fn get_data(selector: SensorSelector, data: &[u8], data_offset: usize) -> Data {
-    let mut result = Data {
-        accel: None,
-        gyro: None,
-        magnet: None,
-        time: None,
-    };
+pub(crate) fn scale_data_si(
+    raw_data: Data,
+    accel_range: AccelerometerRange,
+    gyro_range: GyroscopeRange,
+    magnet_conversion: Option<Conversion>,
+    remap: &AxisRemap,
+    calibration: &Calibration,
+) -> DataSi {
+    let scaled = scale_data(
+        raw_data,
+        accel_range,
+        gyro_range,
+        magnet_conversion,
+        remap,
+        calibration,
+    );
+    DataSi {
+        accel: scaled
+            .accel
+            .map(|d| to_si(d, |v| MetersPerSecondSquared(v * STANDARD_GRAVITY))),
+        gyro: scaled
+            .gyro
+            .map(|d| to_si(d, |v| RadiansPerSecond(v.to_radians()))),
+        magnet: scaled.magnet.map(|d| to_si(d, Microtesla)),
+        time: scaled.time,
+    }
+}
+
+fn to_si<T>(data: Sensor3DDataScaled, unit: impl Fn(f32) -> T) -> Sensor3DDataSi<T> {
+    Sensor3DDataSi {
+        x: unit(data.x),
+        y: unit(data.y),
+        z: unit(data.z),
+    }
+}
+
+pub(crate) fn scale_data_milli(
+    raw_data: Data,
+    accel_range: AccelerometerRange,
+    gyro_range: GyroscopeRange,
+    remap: &AxisRemap,
+) -> DataMilli {
+    let accel_sensitivity = accel_range.sensitivity();
+    let gyro_sensitivity = gyro_range.sensitivity_x10();
+    DataMilli {
+        accel: raw_data
+            .accel
+            .map(|d| scale_milli(remap.remap(d), 1000, accel_sensitivity)),
+        gyro: raw_data
+            .gyro
+            .map(|d| scale_milli(remap.remap(d), 10_000, gyro_sensitivity)),
+        time: raw_data.time,
+    }
+}
+
+fn scale_milli(data: Sensor3DData, numerator: i32, denominator: i32) -> Sensor3DDataMilli {
+    let scale = |v: i16| div_round(i32::from(v) * numerator, denominator);
+    Sensor3DDataMilli {
+        x: scale(data.x),
+        y: scale(data.y),
+        z: scale(data.z),
+    }
+}
+
+/// Division rounding half away from zero. `d` must be positive.
+fn div_round(n: i32, d: i32) -> i32 {
+    if n < 0 {
+        (n - d / 2) / d
+    } else {
+        (n + d / 2) / d
+    }
+}
+
+pub(crate) fn get_data(selector: SensorSelector, data: &[u8], data_offset: usize) -> Data {
+    let mut result = EMPTY_DATA;
     if selector.magnet {
         result.magnet = Some(MagnetometerData {
             axes: get_sensor3d_data(&data[0..6]),
@@ -81,7 +383,7 @@ fn get_data(selector: SensorSelector, data: &[u8], data_offset: usize) -> Data {
     result
 }
 
-fn get_sensor3d_data(data: &[u8]) -> Sensor3DData {
+pub(crate) fn get_sensor3d_data(data: &[u8]) -> Sensor3DData {
     Sensor3DData {
         x: (u16::from(data[0]) | (u16::from(data[1]) << 8)) as i16,
         y: (u16::from(data[2]) | (u16::from(data[3]) << 8)) as i16,
@@ -89,7 +391,7 @@ fn get_sensor3d_data(data: &[u8]) -> Sensor3DData {
     }
 }
 
-fn get_data_addresses(selector: SensorSelector) -> (u8, u8) {
+pub(crate) fn get_data_addresses(selector: SensorSelector) -> (u8, u8) {
     let begin = if selector.magnet {
         Register::MAG
     } else if selector.gyro {
@@ -121,6 +423,100 @@ fn get_data_addresses(selector: SensorSelector) -> (u8, u8) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn tracks_duplicated_and_missed_samples() {
+        let mut report = SampleReport::default();
+        let mut previous = None;
+        let mut track = |time| track_sample(&mut report, &mut previous, Some(time), 256);
+        assert!(track(0xFF_FE10));
+        assert!(!track(0xFF_FEF0));
+        assert!(track(0xFF_FF05));
+        // Two samples missed across the wraparound
+        assert!(track(0x0000_0210));
+        assert_eq!(
+            report,
+            SampleReport {
+                count: 0,
+                duplicates: 1,
+                missed: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn waits_for_all_selected_sensors() {
+        let status = crate::device_impl::decode_status(crate::BitFlags::DRDY_ACC);
+        let selector = SensorSelector::new().accel();
+        assert!(is_data_ready(&status, selector));
+        assert!(!is_data_ready(&status, selector.gyro()));
+    }
+
+    mod milli {
+        use super::*;
+
+        fn raw(v: i16) -> Data {
+            let d = Sensor3DData { x: v, y: v, z: v };
+            Data {
+                accel: Some(d),
+                gyro: Some(d),
+                magnet: None,
+                time: None,
+            }
+        }
+
+        // The integer result is the exactly rounded value and within one
+        // unit of the f32 result, whose precision is lower for large values.
+        fn assert_matches_float(accel_range: AccelerometerRange, gyro_range: GyroscopeRange) {
+            let accel_factor = 1000.0 / f64::from(accel_range.sensitivity());
+            let gyro_factor = 10_000.0 / f64::from(gyro_range.sensitivity_x10());
+            for v in i16::MIN..=i16::MAX {
+                let milli = scale_data_milli(raw(v), accel_range, gyro_range, &AxisRemap::IDENTITY);
+                let float = scale_data(
+                    raw(v),
+                    accel_range,
+                    gyro_range,
+                    None,
+                    &AxisRemap::IDENTITY,
+                    &Calibration::IDENTITY,
+                );
+                let accel = (milli.accel.unwrap().x, float.accel.unwrap().x);
+                let gyro = (milli.gyro.unwrap().x, float.gyro.unwrap().x);
+                for ((m, f), factor) in [(accel, accel_factor), (gyro, gyro_factor)] {
+                    let exact = (f64::from(v) * factor).round() as i32;
+                    assert_eq!(m, exact, "raw value {v}");
+                    assert!((m as f32 - f * 1000.0).abs() <= 1.0, "raw value {v}");
+                }
+            }
+        }
+
+        #[test]
+        fn matches_float_path() {
+            let gyro_ranges = [
+                GyroscopeRange::Scale2000,
+                GyroscopeRange::Scale1000,
+                GyroscopeRange::Scale500,
+                GyroscopeRange::Scale250,
+                GyroscopeRange::Scale125,
+            ];
+            let accel_ranges = [
+                AccelerometerRange::G2,
+                AccelerometerRange::G4,
+                AccelerometerRange::G8,
+            ];
+            for (i, gyro_range) in gyro_ranges.into_iter().enumerate() {
+                assert_matches_float(accel_ranges[i % 3], gyro_range);
+            }
+        }
+
+        #[test]
+        fn rounds_half_away_from_zero() {
+            assert_eq!(div_round(3, 2), 2);
+            assert_eq!(div_round(-3, 2), -2);
+            assert_eq!(div_round(5, 4), 1);
+            assert_eq!(div_round(-5, 4), -1);
+        }
+    }
+
     mod data_addresses {
         use super::*;
         #[test]
@@ -7,9 +7,29 @@ impl Register {
     pub const GYR: u8 = 0x0C;
     pub const ACC: u8 = 0x12;
     pub const SENSORTIME: u8 = 0x18;
+    pub const FIFO_LENGTH_0: u8 = 0x22;
+    pub const FIFO_DATA: u8 = 0x24;
     pub const CMD: u8 = 0x7E;
+    pub const ACC_CONF: u8 = 0x40;
     pub const ACC_RANGE: u8 = 0x41;
+    pub const GYR_CONF: u8 = 0x42;
     pub const GYR_RANGE: u8 = 0x43;
+    pub const MAG_CONF: u8 = 0x44;
+    pub const FIFO_CONFIG_0: u8 = 0x46;
+    pub const FIFO_CONFIG_1: u8 = 0x47;
+    pub const MAG_IF_0: u8 = 0x4B;
+    pub const MAG_IF_1: u8 = 0x4C;
+    pub const MAG_IF_2: u8 = 0x4D;
+    pub const MAG_IF_3: u8 = 0x4E;
+    pub const MAG_IF_4: u8 = 0x4F;
+    pub const INT_EN_1: u8 = 0x51;
+    pub const INT_OUT_CTRL: u8 = 0x53;
+    pub const INT_MAP_1: u8 = 0x56;
+    pub const IF_CONF: u8 = 0x6B;
+    pub const NV_CONF: u8 = 0x70;
+    pub const OFFSET_0: u8 = 0x71;
+    pub const OFFSET_6: u8 = 0x77;
+    pub const SPI_COMM_TEST: u8 = 0x7F;
 }
 
 pub struct BitFlags;
@@ -21,4 +41,31 @@ impl BitFlags {
     pub const FOC_RDY: u8 = 1 << 3;
     pub const MAG_MAN_OP: u8 = 1 << 2;
     pub const GYR_SELF_TEST_OK: u8 = 1 << 1;
+    pub const MAG_MANUAL_EN: u8 = 1 << 7;
+    pub const MAG_OFFSET: u8 = 0b1111 << 2;
+    pub const MAG_RD_BURST: u8 = 0b11;
+    pub const IF_MODE: u8 = 0b11 << 4;
+    pub const IF_MODE_MAG: u8 = 0b10 << 4;
+    pub const SPI3: u8 = 1;
+    pub const I2C_WDT_SEL: u8 = 1 << 1;
+    pub const I2C_WDT_EN: u8 = 1 << 2;
+    pub const ACC_US: u8 = 1 << 7;
+    pub const GYR_OFF_EN: u8 = 1 << 7;
+    pub const ACC_OFF_EN: u8 = 1 << 6;
+    pub const FIFO_GYR_EN: u8 = 1 << 7;
+    pub const FIFO_ACC_EN: u8 = 1 << 6;
+    pub const FIFO_MAG_EN: u8 = 1 << 5;
+    pub const FIFO_HEADER_EN: u8 = 1 << 4;
+    pub const FIFO_TIME_EN: u8 = 1 << 1;
+    pub const FIFO_LENGTH_1: u8 = 0b111;
+    pub const INT_FWM_EN: u8 = 1 << 6;
+    pub const INT_FFULL_EN: u8 = 1 << 5;
+    pub const INT_DRDY_EN: u8 = 1 << 4;
+    pub const INT1_EDGE_CTRL: u8 = 1;
+    pub const INT1_LVL: u8 = 1 << 1;
+    pub const INT1_OD: u8 = 1 << 2;
+    pub const INT1_OUTPUT_EN: u8 = 1 << 3;
+    pub const INT1_DRDY: u8 = 1 << 7;
+    pub const INT1_FWM: u8 = 1 << 6;
+    pub const INT1_FFULL: u8 = 1 << 5;
 }
@@ -0,0 +1,338 @@
+use crate::{
+    config::{
+        decode_accel_config, decode_accel_range, decode_gyro_config, decode_gyro_range,
+        WRITE_DELAY_US,
+    },
+    device_impl::{decode_power_mode, decode_status},
+    interface::{ReadData, WriteData},
+    sequence::Op,
+    AccelerometerConfig, AccelerometerRange, BitFlags, Bmi160, Error, GyroscopeConfig,
+    GyroscopeRange, Register, SensorPowerMode, Status,
+};
+use core::fmt;
+use embedded_hal::delay::DelayNs;
+
+/// Number of registers in the register map
+pub(crate) const REGISTER_COUNT: usize = 128;
+
+/// Register names and addresses. Reserved addresses are not included.
+const REGISTER_NAMES: [(&str, u8); 92] = [
+    ("CHIP_ID", 0x00),
+    ("ERR_REG", 0x02),
+    ("PMU_STATUS", 0x03),
+    ("DATA_MAG_X_L", 0x04),
+    ("DATA_MAG_X_H", 0x05),
+    ("DATA_MAG_Y_L", 0x06),
+    ("DATA_MAG_Y_H", 0x07),
+    ("DATA_MAG_Z_L", 0x08),
+    ("DATA_MAG_Z_H", 0x09),
+    ("DATA_RHALL_L", 0x0A),
+    ("DATA_RHALL_H", 0x0B),
+    ("DATA_GYR_X_L", 0x0C),
+    ("DATA_GYR_X_H", 0x0D),
+    ("DATA_GYR_Y_L", 0x0E),
+    ("DATA_GYR_Y_H", 0x0F),
+    ("DATA_GYR_Z_L", 0x10),
+    ("DATA_GYR_Z_H", 0x11),
+    ("DATA_ACC_X_L", 0x12),
+    ("DATA_ACC_X_H", 0x13),
+    ("DATA_ACC_Y_L", 0x14),
+    ("DATA_ACC_Y_H", 0x15),
+    ("DATA_ACC_Z_L", 0x16),
+    ("DATA_ACC_Z_H", 0x17),
+    ("SENSORTIME_0", 0x18),
+    ("SENSORTIME_1", 0x19),
+    ("SENSORTIME_2", 0x1A),
+    ("STATUS", 0x1B),
+    ("INT_STATUS_0", 0x1C),
+    ("INT_STATUS_1", 0x1D),
+    ("INT_STATUS_2", 0x1E),
+    ("INT_STATUS_3", 0x1F),
+    ("TEMPERATURE_0", 0x20),
+    ("TEMPERATURE_1", 0x21),
+    ("FIFO_LENGTH_0", 0x22),
+    ("FIFO_LENGTH_1", 0x23),
+    ("FIFO_DATA", 0x24),
+    ("ACC_CONF", 0x40),
+    ("ACC_RANGE", 0x41),
+    ("GYR_CONF", 0x42),
+    ("GYR_RANGE", 0x43),
+    ("MAG_CONF", 0x44),
+    ("FIFO_DOWNS", 0x45),
+    ("FIFO_CONFIG_0", 0x46),
+    ("FIFO_CONFIG_1", 0x47),
+    ("MAG_IF_0", 0x4B),
+    ("MAG_IF_1", 0x4C),
+    ("MAG_IF_2", 0x4D),
+    ("MAG_IF_3", 0x4E),
+    ("MAG_IF_4", 0x4F),
+    ("INT_EN_0", 0x50),
+    ("INT_EN_1", 0x51),
+    ("INT_EN_2", 0x52),
+    ("INT_OUT_CTRL", 0x53),
+    ("INT_LATCH", 0x54),
+    ("INT_MAP_0", 0x55),
+    ("INT_MAP_1", 0x56),
+    ("INT_MAP_2", 0x57),
+    ("INT_DATA_0", 0x58),
+    ("INT_DATA_1", 0x59),
+    ("INT_LOWHIGH_0", 0x5A),
+    ("INT_LOWHIGH_1", 0x5B),
+    ("INT_LOWHIGH_2", 0x5C),
+    ("INT_LOWHIGH_3", 0x5D),
+    ("INT_LOWHIGH_4", 0x5E),
+    ("INT_MOTION_0", 0x5F),
+    ("INT_MOTION_1", 0x60),
+    ("INT_MOTION_2", 0x61),
+    ("INT_MOTION_3", 0x62),
+    ("INT_TAP_0", 0x63),
+    ("INT_TAP_1", 0x64),
+    ("INT_ORIENT_0", 0x65),
+    ("INT_ORIENT_1", 0x66),
+    ("INT_FLAT_0", 0x67),
+    ("INT_FLAT_1", 0x68),
+    ("FOC_CONF", 0x69),
+    ("CONF", 0x6A),
+    ("IF_CONF", 0x6B),
+    ("PMU_TRIGGER", 0x6C),
+    ("SELF_TEST", 0x6D),
+    ("NV_CONF", 0x70),
+    ("OFFSET_0", 0x71),
+    ("OFFSET_1", 0x72),
+    ("OFFSET_2", 0x73),
+    ("OFFSET_3", 0x74),
+    ("OFFSET_4", 0x75),
+    ("OFFSET_5", 0x76),
+    ("OFFSET_6", 0x77),
+    ("STEP_CNT_0", 0x78),
+    ("STEP_CNT_1", 0x79),
+    ("STEP_CONF_0", 0x7A),
+    ("STEP_CONF_1", 0x7B),
+    ("CMD", 0x7E),
+];
+
+/// Address ranges of the configuration registers written back by
+/// `restore_registers()`.
+///
+/// MAG_IF_3 and MAG_IF_4 are excluded because writing them in manual mode
+/// triggers a write to the magnetometer. MAG_IF_2 is only written in data
+/// mode because writing it in manual mode triggers a read. SELF_TEST is
+/// excluded because writing it starts a self-test.
+const RESTORED_REGISTERS: [(u8, u8); 5] = [
+    (0x40, 0x47),
+    (0x4B, 0x4D),
+    (0x50, 0x6C),
+    (0x70, 0x77),
+    (0x7A, 0x7B),
+];
+
+/// Snapshot of the whole register map (0x00 to 0x7F)
+///
+/// The `Debug` and `defmt::Format` implementations print each register with
+/// its name, value and decoded content where available.
+#[derive(Clone, Copy, PartialEq)]
+pub struct RegisterDump {
+    registers: [u8; REGISTER_COUNT],
+}
+
+impl RegisterDump {
+    /// Raw register values indexed by address
+    pub fn registers(&self) -> &[u8; REGISTER_COUNT] {
+        &self.registers
+    }
+
+    /// Named registers with their values decoded where available
+    pub fn entries(&self) -> impl Iterator<Item = RegisterEntry> + '_ {
+        REGISTER_NAMES
+            .iter()
+            .map(move |&(name, address)| RegisterEntry {
+                name,
+                address,
+                value: self.registers[usize::from(address)],
+                decoded: decode_register(address, self.registers[usize::from(address)]),
+            })
+    }
+
+    /// Split the data read with `DUMP_READS`. FIFO_DATA is set to 0.
+    pub(crate) fn from_reads(data: &[u8; DUMP_READ_LEN]) -> Self {
+        let fifo_data = usize::from(Register::FIFO_DATA);
+        let mut registers = [0; REGISTER_COUNT];
+        registers[..fifo_data].copy_from_slice(&data[..fifo_data]);
+        registers[fifo_data + 1..].copy_from_slice(&data[fifo_data..]);
+        RegisterDump { registers }
+    }
+
+    /// Register writes and delays done by `restore_registers()` in order
+    pub(crate) fn restore_ops(&self) -> impl Iterator<Item = Op> + '_ {
+        // MAG_IF_1 is written before MAG_IF_2 so the dumped mode is active.
+        let manual_mode =
+            self.registers[usize::from(Register::MAG_IF_1)] & BitFlags::MAG_MANUAL_EN != 0;
+        RESTORED_REGISTERS
+            .iter()
+            .flat_map(|&(begin, end)| begin..=end)
+            .filter(move |&register| !(manual_mode && register == Register::MAG_IF_2))
+            .flat_map(move |register| {
+                [
+                    Op::Write(register, self.registers[usize::from(register)]),
+                    Op::DelayUs(WRITE_DELAY_US),
+                ]
+            })
+    }
+
+    pub(crate) fn ranges(&self) -> (Option<AccelerometerRange>, Option<GyroscopeRange>) {
+        (
+            decode_accel_range::<()>(self.registers[usize::from(Register::ACC_RANGE)]).ok(),
+            decode_gyro_range::<()>(self.registers[usize::from(Register::GYR_RANGE)]).ok(),
+        )
+    }
+}
+
+impl From<[u8; REGISTER_COUNT]> for RegisterDump {
+    /// Create a dump from previously saved register values
+    fn from(registers: [u8; REGISTER_COUNT]) -> Self {
+        RegisterDump { registers }
+    }
+}
+
+impl fmt::Debug for RegisterDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.entries()).finish()
+    }
+}
+
+#[cfg(feature = "defmt-03")]
+impl defmt::Format for RegisterDump {
+    fn format(&self, f: defmt::Formatter) {
+        for entry in self.entries() {
+            defmt::write!(f, "{}\n", entry);
+        }
+    }
+}
+
+/// Register of a [`RegisterDump`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct RegisterEntry {
+    /// Register name as in the datasheet
+    pub name: &'static str,
+    /// Register address
+    pub address: u8,
+    /// Raw value
+    pub value: u8,
+    /// Decoded value
+    pub decoded: DecodedRegister,
+}
+
+/// Decoded register content
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum DecodedRegister {
+    /// No decoding available for this register
+    NotDecoded,
+    /// The register contains a reserved value
+    Reserved,
+    /// PMU_STATUS
+    PowerMode(SensorPowerMode),
+    /// STATUS
+    Status(Status),
+    /// ACC_CONF
+    AccelConfig(AccelerometerConfig),
+    /// ACC_RANGE
+    AccelRange(AccelerometerRange),
+    /// GYR_CONF
+    GyroConfig(GyroscopeConfig),
+    /// GYR_RANGE
+    GyroRange(GyroscopeRange),
+}
+
+fn decode_register(address: u8, value: u8) -> DecodedRegister {
+    let decoded = match address {
+        Register::PMU_STATUS => Ok(DecodedRegister::PowerMode(decode_power_mode(value))),
+        Register::STATUS => Ok(DecodedRegister::Status(decode_status(value))),
+        Register::ACC_CONF => decode_accel_config::<()>(value).map(DecodedRegister::AccelConfig),
+        Register::ACC_RANGE => decode_accel_range::<()>(value).map(DecodedRegister::AccelRange),
+        Register::GYR_CONF => decode_gyro_config::<()>(value).map(DecodedRegister::GyroConfig),
+        Register::GYR_RANGE => decode_gyro_range::<()>(value).map(DecodedRegister::GyroRange),
+        _ => Ok(DecodedRegister::NotDecoded),
+    };
+    decoded.unwrap_or(DecodedRegister::Reserved)
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Read the whole register map.
+    ///
+    /// FIFO_DATA is not read so that no FIFO data is lost and is reported
+    /// as 0. Note that reading some registers like ERR_REG or the interrupt
+    /// status registers may clear them.
+    pub fn dump_registers(&mut self) -> Result<RegisterDump, Error<CommE>> {
+        let mut data = [0; DUMP_READ_LEN];
+        self.run(DUMP_READS, &mut data)?;
+        Ok(RegisterDump::from_reads(&data))
+    }
+
+    /// Write the configuration registers of a dump back to the device.
+    ///
+    /// Only the writable configuration registers are written, waiting after
+    /// each write as required in suspend mode. The power modes are not
+    /// restored and no magnetometer register access is triggered, so the
+    /// magnetometer read address is only restored in data mode. The
+    /// accelerometer and gyroscope ranges are used to scale
+    /// the data from now on.
+    pub fn restore_registers<D: DelayNs>(
+        &mut self,
+        dump: &RegisterDump,
+        delay: &mut D,
+    ) -> Result<(), Error<CommE>> {
+        self.run_delayed(dump.restore_ops(), delay, &mut [])?;
+        let (accel_range, gyro_range) = dump.ranges();
+        self.accel_range = accel_range.unwrap_or(self.accel_range);
+        self.gyro_range = gyro_range.unwrap_or(self.gyro_range);
+        Ok(())
+    }
+}
+
+/// Register bursts read by `dump_registers()`, skipping FIFO_DATA
+pub(crate) const DUMP_READS: [Op; 2] = [Op::Read(0x00, 0x24), Op::Read(0x25, 0x7F - 0x25 + 1)];
+/// Number of bytes read by `DUMP_READS`
+pub(crate) const DUMP_READ_LEN: usize = REGISTER_COUNT - 1;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AccelerometerBandwidth, AccelerometerOdr};
+
+    #[test]
+    fn names_are_sorted_and_unique() {
+        for pair in REGISTER_NAMES.windows(2) {
+            assert!(pair[0].1 < pair[1].1);
+        }
+    }
+
+    #[test]
+    fn can_decode_entries() {
+        let mut registers = [0; REGISTER_COUNT];
+        registers[0x40] = 0x28;
+        registers[0x41] = 0x0C;
+        let dump = RegisterDump::from(registers);
+        let acc_conf = dump.entries().find(|e| e.name == "ACC_CONF").unwrap();
+        assert_eq!(
+            acc_conf,
+            RegisterEntry {
+                name: "ACC_CONF",
+                address: 0x40,
+                value: 0x28,
+                decoded: DecodedRegister::AccelConfig(AccelerometerConfig {
+                    odr: AccelerometerOdr::Hz100,
+                    bandwidth: AccelerometerBandwidth::Normal,
+                }),
+            }
+        );
+        let acc_range = dump.entries().find(|e| e.address == 0x41).unwrap();
+        assert_eq!(acc_range.decoded, DecodedRegister::Reserved);
+        let chip_id = dump.entries().next().unwrap();
+        assert_eq!(chip_id.decoded, DecodedRegister::NotDecoded);
+    }
+}
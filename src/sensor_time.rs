@@ -0,0 +1,150 @@
+use core::time::Duration;
+
+/// Raw 24-bit sensor time counter value
+///
+/// The counter has a resolution of 39.0625 µs and wraps around every
+/// 655.36 s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SensorTime(pub u32);
+
+impl SensorTime {
+    /// Number of ticks after which the counter wraps around
+    pub const WRAP: u32 = 1 << 24;
+
+    /// Counter value in ticks
+    pub fn ticks(self) -> u32 {
+        self.0 & (Self::WRAP - 1)
+    }
+
+    /// Counter value in µs
+    pub fn as_micros(self) -> u32 {
+        // Fits in u32: 2^24 * 625 / 16 < 2^30
+        (self.ticks() as u64 * 625 / 16) as u32
+    }
+
+    /// Counter value as a duration
+    pub fn as_duration(self) -> Duration {
+        Self::ticks_to_duration(self.ticks().into())
+    }
+
+    /// Ticks elapsed since an earlier counter value, assuming at most one
+    /// wraparound in between.
+    pub fn wrapping_sub(self, earlier: SensorTime) -> u32 {
+        self.0.wrapping_sub(earlier.0) & (Self::WRAP - 1)
+    }
+
+    /// Time elapsed since an earlier counter value, assuming at most one
+    /// wraparound in between.
+    pub fn duration_since(self, earlier: SensorTime) -> Duration {
+        Self::ticks_to_duration(self.wrapping_sub(earlier).into())
+    }
+
+    /// Convert a number of ticks to µs, rounding down.
+    pub fn ticks_to_micros(ticks: u64) -> u64 {
+        ticks / 16 * 625 + ticks % 16 * 625 / 16
+    }
+
+    /// Convert a number of ticks to a duration, rounding down to ns.
+    pub fn ticks_to_duration(ticks: u64) -> Duration {
+        // 39062.5 ns per tick
+        Duration::new(ticks / 25_600, (ticks % 25_600 * 78_125 / 2) as u32)
+    }
+}
+
+impl From<u32> for SensorTime {
+    fn from(ticks: u32) -> Self {
+        SensorTime(ticks)
+    }
+}
+
+/// Extends the 24-bit sensor time into a monotonic 64-bit tick count
+///
+/// This must be fed with every sensor time read from the data registers or
+/// the FIFO, in order, at least once per wraparound period of 655.36 s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SensorTimeExtender {
+    last: Option<u64>,
+}
+
+impl SensorTimeExtender {
+    /// Create a new extender. The first value is returned as is.
+    pub fn new() -> Self {
+        SensorTimeExtender { last: None }
+    }
+
+    /// Extend a sensor time read after all previously extended values.
+    ///
+    /// Returns the monotonic tick count. Convert it with
+    /// [`SensorTime::ticks_to_micros()`] or
+    /// [`SensorTime::ticks_to_duration()`].
+    pub fn extend(&mut self, time: SensorTime) -> u64 {
+        let extended = match self.last {
+            Some(last) => last + u64::from(time.wrapping_sub(SensorTime(last as u32))),
+            None => time.ticks().into(),
+        };
+        self.last = Some(extended);
+        extended
+    }
+
+    /// Last extended tick count, if any
+    pub fn last(&self) -> Option<u64> {
+        self.last
+    }
+
+    /// Forget the previous values.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_convert() {
+        assert_eq!(SensorTime(0).as_micros(), 0);
+        assert_eq!(SensorTime(16).as_micros(), 625);
+        assert_eq!(SensorTime(1).as_micros(), 39);
+        assert_eq!(SensorTime(0xFF_FFFF).as_micros(), 655_359_960);
+        assert_eq!(SensorTime(1).as_duration(), Duration::from_nanos(39_062));
+        assert_eq!(SensorTime(25_600).as_duration(), Duration::from_secs(1));
+        assert_eq!(
+            SensorTime::ticks_to_duration(25_601),
+            Duration::new(1, 39_062)
+        );
+        assert_eq!(SensorTime::ticks_to_micros(1 << 40), 42_949_672_960_000);
+    }
+
+    #[test]
+    fn ignores_upper_bits() {
+        assert_eq!(SensorTime(0x100_0010).ticks(), 0x10);
+        assert_eq!(SensorTime(0x100_0010).as_micros(), 625);
+    }
+
+    #[test]
+    fn subtracts_across_wraparound() {
+        assert_eq!(SensorTime(10).wrapping_sub(SensorTime(4)), 6);
+        assert_eq!(SensorTime(2).wrapping_sub(SensorTime(0xFF_FFFE)), 4);
+        assert_eq!(
+            SensorTime(0).duration_since(SensorTime(0xFF_FFF0)),
+            Duration::from_micros(625)
+        );
+    }
+
+    #[test]
+    fn extends_monotonically() {
+        let mut extender = SensorTimeExtender::new();
+        assert_eq!(extender.last(), None);
+        assert_eq!(extender.extend(SensorTime(0xFF_FF00)), 0xFF_FF00);
+        assert_eq!(extender.extend(SensorTime(0xFF_FFFF)), 0xFF_FFFF);
+        assert_eq!(extender.extend(SensorTime(0x10)), 0x100_0010);
+        assert_eq!(extender.extend(SensorTime(0xFF_FF00)), 0x1FF_FF00);
+        assert_eq!(extender.extend(SensorTime(0x20)), 0x200_0020);
+        assert_eq!(extender.last(), Some(0x200_0020));
+        extender.reset();
+        assert_eq!(extender.extend(SensorTime(5)), 5);
+    }
+}
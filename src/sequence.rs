@@ -0,0 +1,90 @@
+//! Register access sequences shared by the blocking and asynchronous drivers
+//!
+//! Each driver only executes the operations, so that the register sequences
+//! and their checks are written once.
+
+/// Longest register burst read: the register dump from 0x25 to 0x7F
+pub(crate) const MAX_READ_LEN: usize = 0x7F - 0x25 + 1;
+
+/// Status polls before a magnetometer interface manual operation times out
+pub(crate) const MAGNET_OP_MAX_POLLS: u32 = 1000;
+
+/// Register access operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    /// Write a value to a register
+    Write(u8, u8),
+    /// Read a register and write it back keeping only the bits in `keep`
+    /// and setting the bits in `bits`
+    Update { register: u8, keep: u8, bits: u8 },
+    /// Read a number of registers from the address into the next output bytes
+    Read(u8, usize),
+    /// Wait in µs
+    DelayUs(u32),
+    /// Wait until the magnetometer interface manual operation is finished
+    WaitMagnet,
+    /// Write a register of the device on the secondary interface
+    WriteMagnet(u8, u8),
+    /// Read a register of the device on the secondary interface into the
+    /// next output byte
+    ReadMagnet(u8),
+}
+
+impl Op {
+    /// Read-modify-write of a register.
+    ///
+    /// `f` must only set or clear fixed bits of the current value.
+    pub(crate) fn update(register: u8, f: impl Fn(u8) -> u8) -> Self {
+        let bits = f(0);
+        Op::Update {
+            register,
+            keep: f(0xFF) & !bits,
+            bits,
+        }
+    }
+}
+
+/// Take the next `len` bytes of the output.
+pub(crate) fn take_output<'a>(output: &mut &'a mut [u8], len: usize) -> &'a mut [u8] {
+    let (data, rest) = core::mem::take(output).split_at_mut(len);
+    *output = rest;
+    data
+}
+
+/// Delay for sequences without delay operations
+pub(crate) struct NoDelay;
+
+impl embedded_hal::delay::DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_update() {
+        let op = Op::update(0x10, |v| (v & !0b1100) | 0b0100);
+        assert_eq!(
+            op,
+            Op::Update {
+                register: 0x10,
+                keep: 0b1111_0011,
+                bits: 0b0100
+            }
+        );
+        assert_eq!(
+            Op::update(0x10, |v| v & !0b1),
+            Op::Update {
+                register: 0x10,
+                keep: 0xFE,
+                bits: 0
+            }
+        );
+    }
+}
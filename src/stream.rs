@@ -0,0 +1,215 @@
+use crate::{
+    fifo::{FifoFrame, FifoFrames, FIFO_BUFFER_LEN},
+    interface::{AsyncReadData, AsyncWriteData},
+    read_sensor_data::{check_period_ticks, track_sample},
+    Bmi160Async, DataScaled, Error, FifoConfig, InterruptPin, InterruptPinConfig, InterruptSource,
+    SampleReport, SensorSelector,
+};
+use embedded_hal::digital::Error as _;
+use embedded_hal_async::digital::Wait;
+
+/// Interrupt awaited by an [`InterruptStream`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum StreamTrigger {
+    /// Read the selected data registers and the sensor time on each
+    /// data-ready interrupt.
+    DataReady {
+        /// Sensors to read
+        selector: SensorSelector,
+        /// Sample period in sensor time ticks, e.g. from
+        /// [`AccelerometerOdr::period_ticks()`](crate::AccelerometerOdr::period_ticks),
+        /// used to detect missed interrupts; must be a power of two up to
+        /// `1 << 23`
+        period_ticks: u32,
+    },
+    /// Read the FIFO content on each FIFO watermark interrupt.
+    FifoWatermark(FifoConfig),
+}
+
+/// Samples returned by [`InterruptStream::next_samples()`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SampleBatch {
+    /// Number of samples written
+    pub count: usize,
+    /// Samples were lost because the FIFO overflowed before it was read
+    pub overflow: bool,
+    /// Number of samples missed before the samples written
+    pub missed: u32,
+}
+
+/// Stream of samples read whenever an interrupt pin signals new data
+///
+/// This owns the driver and the host pin connected to the BMI160 INT1 or
+/// INT2 pin. The interrupt pin is configured as push-pull, active high and
+/// edge triggered.
+///
+/// In FIFO watermark mode the whole FIFO content is read on each interrupt
+/// and kept in an internal buffer until all samples have been returned.
+/// Lost samples are reported through the FIFO overflow (skip) frame.
+/// In data-ready mode, the sensor time is used to count the samples missed
+/// since the previous interrupt.
+#[derive(Debug)]
+pub struct InterruptStream<DI, P> {
+    imu: Bmi160Async<DI>,
+    pin: P,
+    int_pin: InterruptPin,
+    trigger: StreamTrigger,
+    buffer: [u8; FIFO_BUFFER_LEN],
+    pos: usize,
+    len: usize,
+    previous: Option<u32>,
+}
+
+impl<DI, P> InterruptStream<DI, P> {
+    /// Create a new stream.
+    ///
+    /// `pin` is the host pin connected to the BMI160 `int_pin`.
+    /// Call [`start()`](Self::start) to configure the device.
+    pub fn new(
+        imu: Bmi160Async<DI>,
+        pin: P,
+        int_pin: InterruptPin,
+        trigger: StreamTrigger,
+    ) -> Self {
+        InterruptStream {
+            imu,
+            pin,
+            int_pin,
+            trigger,
+            buffer: [0; FIFO_BUFFER_LEN],
+            pos: 0,
+            len: 0,
+            previous: None,
+        }
+    }
+
+    /// Destroy the stream, return the driver and the interrupt pin.
+    ///
+    /// The interrupt configuration is left untouched.
+    pub fn release(self) -> (Bmi160Async<DI>, P) {
+        (self.imu, self.pin)
+    }
+}
+
+impl<DI, CommE, P> InterruptStream<DI, P>
+where
+    DI: AsyncReadData<Error = Error<CommE>> + AsyncWriteData<Error = Error<CommE>>,
+    P: Wait,
+{
+    /// Configure the interrupt pin, the interrupt source and the FIFO if used.
+    ///
+    /// The FIFO content is cleared.
+    ///
+    /// Returns `Error::InvalidInputData` if the `period_ticks` of
+    /// `StreamTrigger::DataReady` is not a power of two up to `1 << 23`.
+    pub async fn start(&mut self) -> Result<(), Error<CommE>> {
+        if let StreamTrigger::DataReady { period_ticks, .. } = self.trigger {
+            check_period_ticks(period_ticks)?;
+        }
+        self.imu
+            .set_interrupt_pin_config(self.int_pin, InterruptPinConfig::default())
+            .await?;
+        let source = match self.trigger {
+            StreamTrigger::DataReady { .. } => InterruptSource::DataReady,
+            StreamTrigger::FifoWatermark(config) => {
+                self.imu.set_fifo_config(config).await?;
+                self.imu.flush_fifo().await?;
+                InterruptSource::FifoWatermark
+            }
+        };
+        self.pos = 0;
+        self.len = 0;
+        self.previous = None;
+        self.imu.enable_interrupt(source, self.int_pin).await
+    }
+
+    /// Wait for new samples and write them scaled into `samples`.
+    ///
+    /// Samples that do not fit are returned by the next call without waiting
+    /// for an interrupt.
+    ///
+    /// Returns `Error::InvalidInputData` if `samples` is empty or the
+    /// `period_ticks` of `StreamTrigger::DataReady` is invalid and
+    /// `Error::Pin` if waiting for the interrupt pin fails.
+    pub async fn next_samples(
+        &mut self,
+        samples: &mut [DataScaled],
+    ) -> Result<SampleBatch, Error<CommE>> {
+        if samples.is_empty() {
+            return Err(Error::InvalidInputData);
+        }
+        match self.trigger {
+            StreamTrigger::DataReady {
+                selector,
+                period_ticks,
+            } => {
+                check_period_ticks(period_ticks)?;
+                let mut report = SampleReport::default();
+                loop {
+                    self.wait_for_interrupt().await?;
+                    samples[0] = self.imu.data_scaled(selector.time()).await?;
+                    if track_sample(
+                        &mut report,
+                        &mut self.previous,
+                        samples[0].time,
+                        period_ticks,
+                    ) {
+                        return Ok(SampleBatch {
+                            count: 1,
+                            overflow: false,
+                            missed: report.missed,
+                        });
+                    }
+                }
+            }
+            StreamTrigger::FifoWatermark(_) => loop {
+                if self.pos == self.len {
+                    self.wait_for_interrupt().await?;
+                    self.len = self.imu.read_fifo(&mut self.buffer).await?.len();
+                    self.pos = 0;
+                }
+                let batch = self.decode_pending(samples);
+                if batch.count > 0 || batch.overflow {
+                    return Ok(batch);
+                }
+            },
+        }
+    }
+
+    async fn wait_for_interrupt(&mut self) -> Result<(), Error<CommE>> {
+        self.pin
+            .wait_for_rising_edge()
+            .await
+            .map_err(|e| Error::Pin(e.kind()))
+    }
+
+    fn decode_pending(&mut self, samples: &mut [DataScaled]) -> SampleBatch {
+        let mut batch = SampleBatch {
+            count: 0,
+            overflow: false,
+            missed: 0,
+        };
+        let mut frames = FifoFrames::new(&self.buffer[self.pos..self.len]);
+        while batch.count < samples.len() {
+            match frames.next() {
+                Some(FifoFrame::Data(data)) => {
+                    samples[batch.count] = self.imu.scale_data(data);
+                    batch.count += 1;
+                }
+                Some(FifoFrame::Skip(n)) => {
+                    batch.overflow = true;
+                    batch.missed += u32::from(n);
+                }
+                Some(_) => (),
+                None => {
+                    self.pos = self.len;
+                    return batch;
+                }
+            }
+        }
+        self.pos = self.len - frames.remaining().len();
+        batch
+    }
+}
@@ -1,11 +1,47 @@
 /// All possible errors in this crate
 #[derive(Debug)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error<CommE> {
     /// I²C / SPI communication error
     Comm(CommE),
     /// Invalid input data provided
     InvalidInputData,
+    /// Interrupt pin error
+    #[cfg(feature = "async")]
+    Pin(embedded_hal::digital::ErrorKind),
+    /// The chip ID read does not belong to a BMI160
+    InvalidChipId(u8),
+    /// A register read from the device contains a reserved value
+    InvalidDeviceData,
+    /// The device did not finish an operation in time
+    Timeout,
+}
+
+/// Primary interface configuration
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct InterfaceConfig {
+    /// Use 3-wire SPI (otherwise 4-wire SPI)
+    pub spi_3wire: bool,
+    /// I2C watchdog timer
+    pub i2c_watchdog: I2cWatchdog,
+}
+
+/// I2C watchdog timer period
+///
+/// When enabled, the I2C interface is reset if SDA is held low for longer
+/// than this period.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum I2cWatchdog {
+    /// Watchdog disabled (default)
+    #[default]
+    Disabled,
+    /// 1 ms
+    Ms1,
+    /// 50 ms
+    Ms50,
 }
 
 /// Sensor power mode
@@ -46,6 +82,15 @@ pub enum AccelerometerRange {
 }
 
 impl AccelerometerRange {
+    /// Sensitivity in LSB/g
+    pub(crate) fn sensitivity(self) -> i32 {
+        match self {
+            AccelerometerRange::G2 => 16384,
+            AccelerometerRange::G4 => 8192,
+            AccelerometerRange::G8 => 4096,
+        }
+    }
+
     pub(crate) fn multiplier(self) -> f32 {
         match self {
             AccelerometerRange::G2 => 1. / 16384.,
@@ -85,6 +130,17 @@ pub enum GyroscopeRange {
 }
 
 impl GyroscopeRange {
+    /// Sensitivity in 0.1 LSB/°/s
+    pub(crate) fn sensitivity_x10(self) -> i32 {
+        match self {
+            GyroscopeRange::Scale2000 => 164,
+            GyroscopeRange::Scale1000 => 328,
+            GyroscopeRange::Scale500 => 656,
+            GyroscopeRange::Scale250 => 1312,
+            GyroscopeRange::Scale125 => 2624,
+        }
+    }
+
     pub(crate) fn multiplier(self) -> f32 {
         match self {
             GyroscopeRange::Scale2000 => 1. / 16.4,
@@ -108,6 +164,305 @@ pub enum MagnetometerPowerMode {
     LowPower,
 }
 
+/// Magnetometer output data rate
+///
+/// This is the rate at which the BMI160 reads the device connected to the
+/// secondary magnetometer interface in data mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum MagnetometerOdr {
+    /// 25/32 Hz
+    Hz0_78 = 0b0001,
+    /// 25/16 Hz
+    Hz1_56 = 0b0010,
+    /// 25/8 Hz
+    Hz3_12 = 0b0011,
+    /// 25/4 Hz
+    Hz6_25 = 0b0100,
+    /// 25/2 Hz
+    Hz12_5 = 0b0101,
+    /// 25 Hz
+    Hz25 = 0b0110,
+    /// 50 Hz
+    Hz50 = 0b0111,
+    /// 100 Hz
+    Hz100 = 0b1000,
+    /// 200 Hz
+    Hz200 = 0b1001,
+    /// 400 Hz
+    Hz400 = 0b1010,
+    /// 800 Hz
+    Hz800 = 0b1011,
+}
+
+impl MagnetometerOdr {
+    pub(crate) fn period_us(self) -> u32 {
+        1_280_000 >> (self as u8 - 1)
+    }
+}
+
+/// Accelerometer output data rate
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum AccelerometerOdr {
+    /// 25/32 Hz
+    Hz0_78 = 0b0001,
+    /// 25/16 Hz
+    Hz1_56 = 0b0010,
+    /// 25/8 Hz
+    Hz3_12 = 0b0011,
+    /// 25/4 Hz
+    Hz6_25 = 0b0100,
+    /// 25/2 Hz
+    Hz12_5 = 0b0101,
+    /// 25 Hz
+    Hz25 = 0b0110,
+    /// 50 Hz
+    Hz50 = 0b0111,
+    /// 100 Hz (default)
+    #[default]
+    Hz100 = 0b1000,
+    /// 200 Hz
+    Hz200 = 0b1001,
+    /// 400 Hz
+    Hz400 = 0b1010,
+    /// 800 Hz
+    Hz800 = 0b1011,
+    /// 1600 Hz
+    Hz1600 = 0b1100,
+}
+
+impl AccelerometerOdr {
+    /// Sample period in sensor time ticks (39.0625 µs)
+    pub fn period_ticks(self) -> u32 {
+        1 << (16 - self as u8)
+    }
+}
+
+/// Accelerometer bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum AccelerometerBandwidth {
+    /// 4-fold oversampling
+    Osr4,
+    /// 2-fold oversampling
+    Osr2,
+    /// Normal filter (default)
+    #[default]
+    Normal,
+    /// Undersampling, averaging 2^n samples. n must be between 0 and 7.
+    ///
+    /// This is required in low power mode.
+    Averaging(u8),
+}
+
+/// Accelerometer output data rate and bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct AccelerometerConfig {
+    /// Output data rate
+    pub odr: AccelerometerOdr,
+    /// Bandwidth
+    pub bandwidth: AccelerometerBandwidth,
+}
+
+/// Gyroscope output data rate
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum GyroscopeOdr {
+    /// 25 Hz
+    Hz25 = 0b0110,
+    /// 50 Hz
+    Hz50 = 0b0111,
+    /// 100 Hz (default)
+    #[default]
+    Hz100 = 0b1000,
+    /// 200 Hz
+    Hz200 = 0b1001,
+    /// 400 Hz
+    Hz400 = 0b1010,
+    /// 800 Hz
+    Hz800 = 0b1011,
+    /// 1600 Hz
+    Hz1600 = 0b1100,
+    /// 3200 Hz
+    Hz3200 = 0b1101,
+}
+
+impl GyroscopeOdr {
+    /// Sample period in sensor time ticks (39.0625 µs)
+    pub fn period_ticks(self) -> u32 {
+        1 << (16 - self as u8)
+    }
+}
+
+/// Gyroscope bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum GyroscopeBandwidth {
+    /// 4-fold oversampling
+    Osr4 = 0b00,
+    /// 2-fold oversampling
+    Osr2 = 0b01,
+    /// Normal filter (default)
+    #[default]
+    Normal = 0b10,
+}
+
+/// Gyroscope output data rate and bandwidth
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct GyroscopeConfig {
+    /// Output data rate
+    pub odr: GyroscopeOdr,
+    /// Bandwidth
+    pub bandwidth: GyroscopeBandwidth,
+}
+
+/// Data-ready and FIFO interrupt configuration
+///
+/// Each source is enabled and mapped to the given pin if present.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct InterruptConfig {
+    /// INT1 pin configuration
+    pub int1: InterruptPinConfig,
+    /// INT2 pin configuration
+    pub int2: InterruptPinConfig,
+    /// Data-ready interrupt pin
+    pub data_ready: Option<InterruptPin>,
+    /// FIFO watermark interrupt pin
+    pub fifo_watermark: Option<InterruptPin>,
+    /// FIFO full interrupt pin
+    pub fifo_full: Option<InterruptPin>,
+}
+
+impl Default for InterruptConfig {
+    /// Interrupts disabled and pin outputs disabled, as after reset
+    fn default() -> Self {
+        let pin = InterruptPinConfig {
+            output_enabled: false,
+            active_high: false,
+            open_drain: false,
+            edge_triggered: false,
+        };
+        InterruptConfig {
+            int1: pin,
+            int2: pin,
+            data_ready: None,
+            fifo_watermark: None,
+            fifo_full: None,
+        }
+    }
+}
+
+/// Accelerometer and gyroscope offset compensation
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Offsets {
+    /// Apply the accelerometer offsets
+    pub accel_enabled: bool,
+    /// Accelerometer X, Y, Z offsets in units of 3.9 mg
+    pub accel: [i8; 3],
+    /// Apply the gyroscope offsets
+    pub gyro_enabled: bool,
+    /// Gyroscope X, Y, Z offsets in units of 0.061 °/s.
+    /// These must be between -512 and 511.
+    pub gyro: [i16; 3],
+}
+
+/// Whole device configuration
+///
+/// See [`Bmi160::configure()`](crate::Bmi160::configure) and
+/// [`Bmi160::read_config()`](crate::Bmi160::read_config).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Config {
+    /// Sensor power modes
+    pub power_mode: SensorPowerMode,
+    /// Accelerometer range
+    pub accel_range: AccelerometerRange,
+    /// Gyroscope range
+    pub gyro_range: GyroscopeRange,
+    /// Accelerometer output data rate and bandwidth
+    pub accel: AccelerometerConfig,
+    /// Gyroscope output data rate and bandwidth
+    pub gyro: GyroscopeConfig,
+    /// FIFO configuration
+    pub fifo: FifoConfig,
+    /// Interrupt configuration
+    pub interrupts: InterruptConfig,
+    /// Offset compensation
+    pub offsets: Offsets,
+}
+
+impl Default for Config {
+    /// Device configuration after reset
+    fn default() -> Self {
+        Config {
+            power_mode: SensorPowerMode {
+                accel: AccelerometerPowerMode::Suspend,
+                gyro: GyroscopePowerMode::Suspend,
+                magnet: MagnetometerPowerMode::Suspend,
+            },
+            accel_range: AccelerometerRange::default(),
+            gyro_range: GyroscopeRange::default(),
+            accel: AccelerometerConfig::default(),
+            gyro: GyroscopeConfig::default(),
+            fifo: FifoConfig {
+                watermark: 4,
+                ..FifoConfig::default()
+            },
+            interrupts: InterruptConfig::default(),
+            offsets: Offsets::default(),
+        }
+    }
+}
+
+/// Magnetometer interface data mode read burst length
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum MagnetometerReadBurst {
+    /// 1 byte
+    Bytes1 = 0b00,
+    /// 2 bytes
+    Bytes2 = 0b01,
+    /// 6 bytes
+    Bytes6 = 0b10,
+    /// 8 bytes
+    Bytes8 = 0b11,
+}
+
+/// Magnetometer interface data mode configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct MagnetometerConfig {
+    /// Output data rate
+    pub odr: MagnetometerOdr,
+    /// Number of bytes read from the device in each burst
+    pub read_burst: MagnetometerReadBurst,
+    /// Trigger-readout offset in units of 2.5 ms (0-15)
+    pub offset: u8,
+}
+
+/// BMM150 magnetometer repetitions preset
+///
+/// These correspond to the presets recommended by Bosch in the BMM150
+/// datasheet. Higher presets reduce noise at the cost of power consumption.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Bmm150Preset {
+    /// Low power preset: 3 XY repetitions, 3 Z repetitions
+    LowPower,
+    /// Regular preset: 9 XY repetitions, 15 Z repetitions
+    #[default]
+    Regular,
+    /// Enhanced regular preset: 15 XY repetitions, 27 Z repetitions
+    EnhancedRegular,
+    /// High accuracy preset: 47 XY repetitions, 83 Z repetitions
+    HighAccuracy,
+}
+
 /// Sensor status flags
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -128,6 +483,72 @@ pub struct Status {
     pub gyro_self_test_ok: bool,
 }
 
+/// FIFO configuration
+///
+/// The FIFO is always operated in header mode.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct FifoConfig {
+    /// Store accelerometer data in the FIFO
+    pub accel: bool,
+    /// Store gyroscope data in the FIFO
+    pub gyro: bool,
+    /// Store magnetometer data in the FIFO
+    pub magnet: bool,
+    /// Return a sensor time frame after the last data frame
+    pub time: bool,
+    /// FIFO watermark level in units of 4 bytes
+    pub watermark: u8,
+}
+
+/// Interrupt output pin
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum InterruptPin {
+    /// INT1 pin
+    Int1,
+    /// INT2 pin
+    Int2,
+}
+
+/// Interrupt output pin electrical configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct InterruptPinConfig {
+    /// Enable the pin as output
+    pub output_enabled: bool,
+    /// Active high (otherwise active low)
+    pub active_high: bool,
+    /// Open drain (otherwise push-pull)
+    pub open_drain: bool,
+    /// Edge triggered (otherwise level triggered)
+    pub edge_triggered: bool,
+}
+
+impl Default for InterruptPinConfig {
+    /// Push-pull, active high, edge triggered output
+    fn default() -> Self {
+        InterruptPinConfig {
+            output_enabled: true,
+            active_high: true,
+            open_drain: false,
+            edge_triggered: true,
+        }
+    }
+}
+
+/// Interrupt source
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum InterruptSource {
+    /// New data is available in the data registers
+    DataReady,
+    /// The FIFO fill level reached the watermark
+    FifoWatermark,
+    /// The FIFO is full
+    FifoFull,
+}
+
 /// Sensor data read selector
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -205,6 +626,9 @@ pub struct Sensor3DData {
 }
 
 /// Magnetometer data
+///
+/// This contains the raw content of the magnetometer data registers as read
+/// from the device connected to the secondary interface.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct MagnetometerData {
@@ -248,12 +672,90 @@ pub struct DataScaled {
     pub accel: Option<Sensor3DDataScaled>,
     /// Gyroscope data (if selected)
     pub gyro: Option<Sensor3DDataScaled>,
-    /// Magnetometer data (if selected)
-    pub magnet: Option<MagnetometerData>,
+    /// Magnetometer data in µT (if selected and a magnetometer was set up)
+    pub magnet: Option<Sensor3DDataScaled>,
+    /// Time data (if selected)
+    pub time: Option<u32>,
+}
+
+/// 3D data scaled with integer math
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Sensor3DDataMilli {
+    /// X axis data
+    pub x: i32,
+    /// Y axis data
+    pub y: i32,
+    /// Z axis data
+    pub z: i32,
+}
+
+/// Sensor data read scaled with integer math
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct DataMilli {
+    /// Accelerometer data in milli-g (if selected)
+    pub accel: Option<Sensor3DDataMilli>,
+    /// Gyroscope data in milli-°/s (if selected)
+    pub gyro: Option<Sensor3DDataMilli>,
+    /// Time data (if selected)
+    pub time: Option<u32>,
+}
+
+/// Acceleration in m/s²
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct MetersPerSecondSquared(pub f32);
+
+/// Angular rate in rad/s
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct RadiansPerSecond(pub f32);
+
+/// Magnetic flux density in µT
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Microtesla(pub f32);
+
+/// 3D data in physical units
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct Sensor3DDataSi<T> {
+    /// X axis data
+    pub x: T,
+    /// Y axis data
+    pub y: T,
+    /// Z axis data
+    pub z: T,
+}
+
+/// Sensor data read in physical units
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct DataSi {
+    /// Accelerometer data (if selected)
+    pub accel: Option<Sensor3DDataSi<MetersPerSecondSquared>>,
+    /// Gyroscope data (if selected)
+    pub gyro: Option<Sensor3DDataSi<RadiansPerSecond>>,
+    /// Magnetometer data (if selected and a magnetometer was set up)
+    pub magnet: Option<Sensor3DDataSi<Microtesla>>,
     /// Time data (if selected)
     pub time: Option<u32>,
 }
 
+/// Result of [`read_samples()`](crate::Bmi160::read_samples)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct SampleReport {
+    /// Number of samples written
+    pub count: usize,
+    /// Number of samples read again before new data was available. These
+    /// are discarded.
+    pub duplicates: u32,
+    /// Number of samples missed between the samples written
+    pub missed: u32,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,290 @@
+//! Typestate driver enforcing the sensor power modes at compile time
+//!
+//! [`TypedBmi160`] wraps a [`Bmi160`] and tracks the accelerometer and
+//! gyroscope power modes in its type. Power mode transitions consume the
+//! handle and return a new one. The accelerometer data methods are only
+//! available while the accelerometer is powered and the gyroscope data
+//! methods only while the gyroscope is in normal mode.
+//!
+//! ```no_run
+//! # use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Mock};
+//! use bmi160::{Bmi160, SlaveAddr};
+//!
+//! # let dev = Mock::new(&[]);
+//! # let mut delay = NoopDelay::new();
+//! let imu = Bmi160::new_with_i2c(dev, SlaveAddr::default());
+//! let imu = imu.into_typestate().unwrap();
+//! // imu.accel() does not compile here: the accelerometer is suspended.
+//! let mut imu = imu.into_accel_normal(&mut delay).unwrap();
+//! let accel = imu.accel_scaled().unwrap();
+//! ```
+//!
+//! Reading the accelerometer while it is suspended does not compile:
+//!
+//! ```compile_fail
+//! # use embedded_hal_mock::eh1::i2c::Mock;
+//! # use bmi160::{Bmi160, SlaveAddr};
+//! # let dev = Mock::new(&[]);
+//! let mut imu = Bmi160::new_with_i2c(dev, SlaveAddr::default())
+//!     .into_typestate()
+//!     .unwrap();
+//! let accel = imu.accel();
+//! ```
+//!
+//! The magnetometer power mode is not tracked.
+
+use crate::{
+    config::WRITE_DELAY_US,
+    interface::{ReadData, WriteData},
+    read_sensor_data::get_sensor3d_data,
+    sequence::Op,
+    AccelerometerPowerMode, AccelerometerRange, BitFlags, Bmi160, Data, Error, GyroscopePowerMode,
+    GyroscopeRange, Register, Sensor3DData, Sensor3DDataScaled, SensorSelector,
+};
+use core::marker::PhantomData;
+use embedded_hal::delay::DelayNs;
+
+/// Maximum accelerometer start-up time from suspend in µs
+const ACCEL_STARTUP_US: u32 = 3800;
+
+/// Sensor in suspend mode
+#[derive(Debug)]
+pub struct Suspended;
+/// Sensor in normal mode
+#[derive(Debug)]
+pub struct Normal;
+/// Accelerometer in low power mode
+#[derive(Debug)]
+pub struct LowPower;
+/// Gyroscope in fast start-up mode
+#[derive(Debug)]
+pub struct FastStartUp;
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::Suspended {}
+    impl Sealed for super::Normal {}
+    impl Sealed for super::LowPower {}
+    impl Sealed for super::FastStartUp {}
+}
+
+/// Accelerometer power mode state
+pub trait AccelState: private::Sealed {}
+impl AccelState for Suspended {}
+impl AccelState for Normal {}
+impl AccelState for LowPower {}
+
+/// Accelerometer power mode state in which data is available
+pub trait AccelPowered: AccelState {}
+impl AccelPowered for Normal {}
+impl AccelPowered for LowPower {}
+
+/// Gyroscope power mode state
+pub trait GyroState: private::Sealed {
+    /// Maximum start-up time from this state to normal mode in µs
+    #[doc(hidden)]
+    const NORMAL_STARTUP_US: u32;
+}
+impl GyroState for Suspended {
+    const NORMAL_STARTUP_US: u32 = 80_000;
+}
+impl GyroState for Normal {
+    const NORMAL_STARTUP_US: u32 = 0;
+}
+impl GyroState for FastStartUp {
+    const NORMAL_STARTUP_US: u32 = 10_000;
+}
+
+/// BMI160 driver with the accelerometer and gyroscope power modes in its type
+///
+/// See the [module documentation](self).
+#[derive(Debug)]
+pub struct TypedBmi160<DI, A, G> {
+    imu: Bmi160<DI>,
+    _state: PhantomData<(A, G)>,
+}
+
+impl<DI, CommE> Bmi160<DI>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+{
+    /// Suspend the accelerometer and gyroscope and convert into a
+    /// [`TypedBmi160`].
+    pub fn into_typestate(mut self) -> Result<TypedBmi160<DI, Suspended, Suspended>, Error<CommE>> {
+        self.set_accel_power_mode(AccelerometerPowerMode::Suspend)?;
+        self.set_gyro_power_mode(GyroscopePowerMode::Suspend)?;
+        Ok(TypedBmi160::new(self))
+    }
+}
+
+impl<DI, A, G> TypedBmi160<DI, A, G> {
+    fn new(imu: Bmi160<DI>) -> Self {
+        TypedBmi160 {
+            imu,
+            _state: PhantomData,
+        }
+    }
+
+    /// Return the dynamic driver. The power modes are kept.
+    pub fn into_inner(self) -> Bmi160<DI> {
+        self.imu
+    }
+}
+
+impl<DI, CommE, A, G> TypedBmi160<DI, A, G>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+    A: AccelState,
+    G: GyroState,
+{
+    /// Put the accelerometer in normal mode and wait until it has started.
+    pub fn into_accel_normal<D: DelayNs>(
+        self,
+        delay: &mut D,
+    ) -> Result<TypedBmi160<DI, Normal, G>, Error<CommE>> {
+        self.into_accel_powered(AccelerometerPowerMode::Normal, delay)
+    }
+
+    /// Put the accelerometer in low power mode and wait until it has started.
+    ///
+    /// Undersampling averaging 2^`averaging` samples is enabled first, as
+    /// required in low power mode. The output data rate is kept.
+    ///
+    /// Returns `Error::InvalidInputData` if `averaging` is above 7.
+    pub fn into_accel_low_power<D: DelayNs>(
+        mut self,
+        averaging: u8,
+        delay: &mut D,
+    ) -> Result<TypedBmi160<DI, LowPower, G>, Error<CommE>> {
+        if averaging > 7 {
+            return Err(Error::InvalidInputData);
+        }
+        let acc_conf = Op::update(Register::ACC_CONF, |v| {
+            (v & 0x0F) | BitFlags::ACC_US | averaging << 4
+        });
+        self.imu
+            .run_delayed([acc_conf, Op::DelayUs(WRITE_DELAY_US)], delay, &mut [])?;
+        self.into_accel_powered(AccelerometerPowerMode::LowPower, delay)
+    }
+
+    /// Suspend the accelerometer.
+    pub fn into_accel_suspend(mut self) -> Result<TypedBmi160<DI, Suspended, G>, Error<CommE>> {
+        self.imu
+            .set_accel_power_mode(AccelerometerPowerMode::Suspend)?;
+        Ok(TypedBmi160::new(self.imu))
+    }
+
+    /// Put the gyroscope in normal mode and wait until it has started.
+    pub fn into_gyro_normal<D: DelayNs>(
+        mut self,
+        delay: &mut D,
+    ) -> Result<TypedBmi160<DI, A, Normal>, Error<CommE>> {
+        self.imu.set_gyro_power_mode(GyroscopePowerMode::Normal)?;
+        delay.delay_us(G::NORMAL_STARTUP_US);
+        Ok(TypedBmi160::new(self.imu))
+    }
+
+    /// Put the gyroscope in fast start-up mode.
+    pub fn into_gyro_fast_startup(
+        mut self,
+    ) -> Result<TypedBmi160<DI, A, FastStartUp>, Error<CommE>> {
+        self.imu
+            .set_gyro_power_mode(GyroscopePowerMode::FastStartUp)?;
+        Ok(TypedBmi160::new(self.imu))
+    }
+
+    /// Suspend the gyroscope.
+    pub fn into_gyro_suspend(mut self) -> Result<TypedBmi160<DI, A, Suspended>, Error<CommE>> {
+        self.imu.set_gyro_power_mode(GyroscopePowerMode::Suspend)?;
+        Ok(TypedBmi160::new(self.imu))
+    }
+
+    /// Set the accelerometer range
+    pub fn set_accel_range(&mut self, range: AccelerometerRange) -> Result<(), Error<CommE>> {
+        self.imu.set_accel_range(range)
+    }
+
+    /// Set the gyro range
+    pub fn set_gyro_range(&mut self, range: GyroscopeRange) -> Result<(), Error<CommE>> {
+        self.imu.set_gyro_range(range)
+    }
+
+    fn into_accel_powered<A2, D: DelayNs>(
+        mut self,
+        mode: AccelerometerPowerMode,
+        delay: &mut D,
+    ) -> Result<TypedBmi160<DI, A2, G>, Error<CommE>> {
+        self.imu.set_accel_power_mode(mode)?;
+        delay.delay_us(ACCEL_STARTUP_US);
+        Ok(TypedBmi160::new(self.imu))
+    }
+
+    /// Read sensor data in the sensor axes
+    fn read_sensor3d(&mut self, register: u8) -> Result<Sensor3DData, Error<CommE>> {
+        let mut data = [0; 7];
+        data[0] = register;
+        self.imu.iface.read_data(&mut data)?;
+        Ok(get_sensor3d_data(&data[1..]))
+    }
+}
+
+impl<DI, CommE, A, G> TypedBmi160<DI, A, G>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+    A: AccelPowered,
+    G: GyroState,
+{
+    /// Read the latest accelerometer data
+    pub fn accel(&mut self) -> Result<Sensor3DData, Error<CommE>> {
+        let data = self.read_sensor3d(Register::ACC)?;
+        Ok(self.imu.axis_remap.remap(data))
+    }
+
+    /// Read the latest accelerometer data scaled to the accelerometer range in g
+    pub fn accel_scaled(&mut self) -> Result<Sensor3DDataScaled, Error<CommE>> {
+        let data = self.read_sensor3d(Register::ACC)?;
+        let data = scale(data, self.imu.accel_range.multiplier());
+        let data = self.imu.calibration.accel.apply(data);
+        Ok(self.imu.axis_remap.remap_scaled(data))
+    }
+}
+
+impl<DI, CommE, A> TypedBmi160<DI, A, Normal>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+    A: AccelState,
+{
+    /// Read the latest gyroscope data
+    pub fn gyro(&mut self) -> Result<Sensor3DData, Error<CommE>> {
+        let data = self.read_sensor3d(Register::GYR)?;
+        Ok(self.imu.axis_remap.remap(data))
+    }
+
+    /// Read the latest gyroscope data scaled to the gyroscope range in °/s
+    pub fn gyro_scaled(&mut self) -> Result<Sensor3DDataScaled, Error<CommE>> {
+        let data = self.read_sensor3d(Register::GYR)?;
+        let data = scale(data, self.imu.gyro_range.multiplier());
+        let data = self.imu.calibration.gyro.apply(data);
+        Ok(self.imu.axis_remap.remap_scaled(data))
+    }
+}
+
+impl<DI, CommE, A> TypedBmi160<DI, A, Normal>
+where
+    DI: ReadData<Error = Error<CommE>> + WriteData<Error = Error<CommE>>,
+    A: AccelPowered,
+{
+    /// Read the latest accelerometer, gyroscope and sensor time data
+    /// at once.
+    pub fn data(&mut self) -> Result<Data, Error<CommE>> {
+        self.imu.data(SensorSelector::new().accel().gyro().time())
+    }
+}
+
+fn scale(data: Sensor3DData, multiplier: f32) -> Sensor3DDataScaled {
+    Sensor3DDataScaled {
+        x: f32::from(data.x) * multiplier,
+        y: f32::from(data.y) * multiplier,
+        z: f32::from(data.z) * multiplier,
+    }
+}
@@ -0,0 +1,392 @@
+#![cfg(feature = "async")]
+
+use bmi160::{
+    interface, AccelerometerPowerMode, AccelerometerRange, Bmi160Async, Bmm150Preset, Config, Data,
+    DataScaled, FifoConfig, GyroscopePowerMode, GyroscopeRange, InterruptPin, InterruptStream,
+    MagnetometerPowerMode, SampleBatch, Sensor3DData, Sensor3DDataScaled, SensorPowerMode,
+    SensorSelector, SlaveAddr, Status, StreamTrigger,
+};
+use embassy_futures::block_on;
+mod common;
+use crate::common::{Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{
+    delay::NoopDelay,
+    digital::{Edge, Mock as PinMock, Transaction as PinTrans},
+    i2c::{Mock as I2cMock, Transaction as I2cTrans},
+    spi::{Mock as SpiMock, Transaction as SpiTrans},
+};
+
+fn new_i2c(transactions: &[I2cTrans]) -> Bmi160Async<interface::I2cInterface<I2cMock>> {
+    Bmi160Async::new_with_i2c(I2cMock::new(transactions), SlaveAddr::default())
+}
+
+fn destroy_i2c(imu: Bmi160Async<interface::I2cInterface<I2cMock>>) {
+    imu.destroy().done();
+}
+
+#[test]
+fn can_create_and_destroy_spi() {
+    let imu = Bmi160Async::new_with_spi(SpiMock::<u8>::new(&[]));
+    imu.destroy().done();
+}
+
+#[test]
+fn can_get_chip_id() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::CHIPID],
+        vec![0xD1],
+    )]);
+    assert_eq!(0xD1, block_on(imu.chip_id()).unwrap());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_get_chip_id_spi() {
+    let mut imu = Bmi160Async::new_with_spi(SpiMock::new(&[
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(vec![Register::CHIPID | 0x80, 0], vec![0, 0xD1]),
+        SpiTrans::transaction_end(),
+    ]));
+    assert_eq!(0xD1, block_on(imu.chip_id()).unwrap());
+    imu.destroy().done();
+}
+
+#[test]
+fn can_init_spi() {
+    let mut imu = Bmi160Async::new_with_spi(SpiMock::new(&[
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(vec![Register::SPI_COMM_TEST | 0x80, 0], vec![0, 0]),
+        SpiTrans::transaction_end(),
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(vec![Register::CHIPID | 0x80, 0], vec![0, 0xD1]),
+        SpiTrans::transaction_end(),
+    ]));
+    block_on(imu.init()).unwrap();
+    imu.destroy().done();
+}
+
+#[test]
+fn can_get_power_mode() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::PMU_STATUS],
+        vec![0b0010_1101],
+    )]);
+    let pm = block_on(imu.power_mode()).unwrap();
+    assert_eq!(
+        SensorPowerMode {
+            accel: AccelerometerPowerMode::LowPower,
+            gyro: GyroscopePowerMode::FastStartUp,
+            magnet: MagnetometerPowerMode::Normal,
+        },
+        pm
+    );
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_get_status() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::STATUS],
+        vec![0b1000_0010],
+    )]);
+    let st = block_on(imu.status()).unwrap();
+    assert_eq!(
+        Status {
+            accel_data_ready: true,
+            gyro_data_ready: false,
+            magnet_data_ready: false,
+            nvm_ready: false,
+            foc_ready: false,
+            magnet_manual_op: false,
+            gyro_self_test_ok: true,
+        },
+        st
+    );
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_set_power_modes() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0001]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0111]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_1010]),
+    ]);
+    block_on(imu.set_accel_power_mode(AccelerometerPowerMode::Normal)).unwrap();
+    block_on(imu.set_gyro_power_mode(GyroscopePowerMode::FastStartUp)).unwrap();
+    block_on(imu.set_magnet_power_mode(MagnetometerPowerMode::LowPower)).unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_set_ranges_and_get_scaled_data() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::ACC_RANGE, 0b0000_1000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::GYR_RANGE, 0b0000_0100]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::GYR],
+            vec![
+                0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x10, 0x00, 0x20, 0x00, 0xF0,
+            ],
+        ),
+    ]);
+    block_on(imu.set_accel_range(AccelerometerRange::G8)).unwrap();
+    block_on(imu.set_gyro_range(GyroscopeRange::Scale125)).unwrap();
+    let data = block_on(imu.data_scaled(SensorSelector::new().accel().gyro())).unwrap();
+    let accel = data.accel.unwrap();
+    assert_eq!((1.0, 2.0, -1.0), (accel.x, accel.y, accel.z));
+    let gyro = data.gyro.unwrap();
+    assert_eq!(1. / 262.4, gyro.x);
+    assert_eq!(2. / 262.4, gyro.y);
+    assert_eq!(3. / 262.4, gyro.z);
+    assert_eq!(None, data.magnet);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_get_data() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::ACC],
+        vec![0x0F, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17],
+    )]);
+    let result = block_on(imu.data(SensorSelector::new().accel().time())).unwrap();
+    let expected = Data {
+        magnet: None,
+        gyro: None,
+        accel: Some(Sensor3DData {
+            x: 0x100F,
+            y: 0x1211,
+            z: 0x1413,
+        }),
+        time: Some(0x171615),
+    };
+    assert_eq!(expected, result);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_access_magnet_registers() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_4, 0x01]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_3, 0x4B]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![1 << 2]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, 0x40]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG], vec![0x32]),
+    ]);
+    block_on(imu.write_magnet_register(0x4B, 0x01)).unwrap();
+    assert_eq!(0x32, block_on(imu.read_magnet_register(0x40)).unwrap());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_setup_bmm150() {
+    let mut trans = vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_1001]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::IF_CONF], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::IF_CONF, 0b0010_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_0, 0x20]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b1000_0000]),
+    ];
+    for (register, data) in [(0x4B, 0x01), (0x51, 0x01), (0x52, 0x02)] {
+        trans.extend([
+            I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_4, data]),
+            I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_3, register]),
+            I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        ]);
+    }
+    for register in 0x5D..=0x71 {
+        trans.extend([
+            I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, register]),
+            I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+            I2cTrans::write_read(DEV_ADDR, vec![Register::MAG], vec![0]),
+        ]);
+    }
+    trans.extend([
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_4, 0x02]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_3, 0x4C]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, 0x42]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_CONF, 0b0101]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1000_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b1000_0011]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1000_0011]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b0000_0011]),
+    ]);
+    let mut imu = new_i2c(&trans);
+    block_on(imu.setup_bmm150(&mut NoopDelay::new(), Bmm150Preset::LowPower)).unwrap();
+    destroy_i2c(imu);
+}
+
+const EMPTY_SAMPLE: DataScaled = DataScaled {
+    accel: None,
+    gyro: None,
+    magnet: None,
+    time: None,
+};
+
+fn accel_sample(x: f32) -> DataScaled {
+    DataScaled {
+        accel: Some(Sensor3DDataScaled { x, y: 0.0, z: 1.0 }),
+        ..EMPTY_SAMPLE
+    }
+}
+
+#[test]
+fn can_stream_data_ready_samples() {
+    let imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_OUT_CTRL, 0b0000_1011]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_MAP_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_MAP_1, 0b1000_0000]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_EN_1, 0b0001_0000]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC],
+            vec![0x00, 0x20, 0x00, 0x00, 0x00, 0x40, 0x00, 0x01, 0x00],
+        ),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC],
+            vec![0x00, 0x10, 0x00, 0x00, 0x00, 0x40, 0xC0, 0x01, 0x00],
+        ),
+    ]);
+    let pin = PinMock::new(&[
+        PinTrans::wait_for_edge(Edge::Rising),
+        PinTrans::wait_for_edge(Edge::Rising),
+    ]);
+    let trigger = StreamTrigger::DataReady {
+        selector: SensorSelector::new().accel(),
+        period_ticks: 64,
+    };
+    let mut stream = InterruptStream::new(imu, pin, InterruptPin::Int1, trigger);
+    block_on(stream.start()).unwrap();
+    let mut samples = [EMPTY_SAMPLE; 2];
+    let batch = block_on(stream.next_samples(&mut samples)).unwrap();
+    assert_eq!(
+        SampleBatch {
+            count: 1,
+            overflow: false,
+            missed: 0
+        },
+        batch
+    );
+    assert_eq!(
+        DataScaled {
+            time: Some(0x100),
+            ..accel_sample(0.5)
+        },
+        samples[0]
+    );
+    let batch = block_on(stream.next_samples(&mut samples)).unwrap();
+    assert_eq!(
+        SampleBatch {
+            count: 1,
+            overflow: false,
+            missed: 2
+        },
+        batch
+    );
+    assert_eq!(
+        DataScaled {
+            time: Some(0x1C0),
+            ..accel_sample(0.25)
+        },
+        samples[0]
+    );
+    let (imu, mut pin) = stream.release();
+    destroy_i2c(imu);
+    pin.done();
+}
+
+#[test]
+fn can_stream_fifo_samples() {
+    let fifo = [
+        0x84, 0x00, 0x10, 0x00, 0x00, 0x00, 0x40, // accel
+        0x40, 0x03, // skip
+        0x84, 0x00, 0x20, 0x00, 0x00, 0x00, 0x40, // accel
+        0x84, 0x00, 0x30, 0x00, 0x00, 0x00, 0x40, // accel
+        0x80, 0x80, 0x80, 0x80,
+    ];
+    let imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_OUT_CTRL, 0b1011_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_0, 10]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_1, 0b0101_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0xB0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_MAP_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_MAP_1, 0b0000_0100]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_EN_1, 0b0100_0000]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_LENGTH_0], vec![23, 0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_DATA], fifo.to_vec()),
+    ]);
+    let pin = PinMock::new(&[PinTrans::wait_for_edge(Edge::Rising)]);
+    let trigger = StreamTrigger::FifoWatermark(FifoConfig {
+        accel: true,
+        watermark: 10,
+        ..Default::default()
+    });
+    let mut stream = InterruptStream::new(imu, pin, InterruptPin::Int2, trigger);
+    block_on(stream.start()).unwrap();
+    let mut samples = [EMPTY_SAMPLE; 2];
+    let batch = block_on(stream.next_samples(&mut samples)).unwrap();
+    assert_eq!(
+        SampleBatch {
+            count: 2,
+            overflow: true,
+            missed: 3
+        },
+        batch
+    );
+    assert_eq!([accel_sample(0.25), accel_sample(0.5)], samples);
+    let batch = block_on(stream.next_samples(&mut samples)).unwrap();
+    assert_eq!(
+        SampleBatch {
+            count: 1,
+            overflow: false,
+            missed: 0
+        },
+        batch
+    );
+    assert_eq!(accel_sample(0.75), samples[0]);
+    let (imu, mut pin) = stream.release();
+    destroy_i2c(imu);
+    pin.done();
+}
+
+#[test]
+fn can_configure_default() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::ACC_CONF, 0x28]),
+        I2cTrans::write(DEV_ADDR, vec![Register::ACC_RANGE, 0x03]),
+        I2cTrans::write(DEV_ADDR, vec![Register::GYR_CONF, 0x28]),
+        I2cTrans::write(DEV_ADDR, vec![Register::GYR_RANGE, 0x00]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_0, 0x04]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_1, 0x10]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_OUT_CTRL, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_MAP_1, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_EN_1, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 1, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 2, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 3, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 4, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 5, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 6, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0100]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_1000]),
+    ]);
+    block_on(imu.configure(&Config::default(), &mut NoopDelay::new())).unwrap();
+    destroy_i2c(imu);
+}
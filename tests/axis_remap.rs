@@ -0,0 +1,100 @@
+use bmi160::{
+    AxisRemap, Data, InvalidAxisRemap, MagnetometerData, Sensor3DData, Sensor3DDataScaled,
+    SensorSelector, SignedAxis,
+};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::i2c::Transaction as I2cTrans;
+
+const ROTATION: AxisRemap = AxisRemap::Rotation {
+    x: SignedAxis::PosY,
+    y: SignedAxis::NegX,
+    z: SignedAxis::PosZ,
+};
+
+#[test]
+fn identity_is_default() {
+    let imu = new_i2c(&[]);
+    assert_eq!(imu.axis_remap(), AxisRemap::IDENTITY);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn cannot_set_reflection() {
+    let mut imu = new_i2c(&[]);
+    let remap = AxisRemap::Rotation {
+        x: SignedAxis::NegX,
+        y: SignedAxis::PosY,
+        z: SignedAxis::PosZ,
+    };
+    assert_eq!(imu.set_axis_remap(remap), Err(InvalidAxisRemap));
+    assert_eq!(imu.axis_remap(), AxisRemap::IDENTITY);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn remaps_accel_and_gyro_data() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::MAG],
+        vec![
+            1, 0, 2, 0, 3, 0, 0x34, 0x12, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0, 9, 0,
+        ],
+    )]);
+    imu.set_axis_remap(ROTATION).unwrap();
+    let result = imu
+        .data(SensorSelector::new().magnet().gyro().accel())
+        .unwrap();
+    assert_eq!(
+        result.magnet,
+        Some(MagnetometerData {
+            axes: Sensor3DData { x: 1, y: 2, z: 3 },
+            hall_resistence: 0x1234,
+        })
+    );
+    assert_eq!(result.gyro, Some(Sensor3DData { x: 5, y: -4, z: 6 }));
+    assert_eq!(result.accel, Some(Sensor3DData { x: 8, y: -7, z: 9 }));
+    destroy_i2c(imu);
+}
+
+#[test]
+fn remaps_scaled_data_with_matrix() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::ACC],
+        vec![0, 0x40, 0, 0, 0, 0xC0],
+    )]);
+    let matrix = [[0.0, 0.0, 1.0], [0.0, 1.0, 0.0], [-1.0, 0.0, 0.0]];
+    imu.set_axis_remap(AxisRemap::Matrix(matrix)).unwrap();
+    let result = imu.data_scaled(SensorSelector::new().accel()).unwrap();
+    assert_eq!(
+        result.accel,
+        Some(Sensor3DDataScaled {
+            x: -1.0,
+            y: 0.0,
+            z: -1.0,
+        })
+    );
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_remap_decoded_data() {
+    let magnet = MagnetometerData {
+        axes: Sensor3DData { x: 1, y: 2, z: 3 },
+        hall_resistence: 0x1234,
+    };
+    let data = Data {
+        accel: Some(Sensor3DData { x: 7, y: 8, z: 9 }),
+        gyro: None,
+        magnet: Some(magnet),
+        time: Some(5),
+    };
+    assert_eq!(
+        ROTATION.remap_data(data),
+        Data {
+            accel: Some(Sensor3DData { x: 8, y: -7, z: 9 }),
+            ..data
+        }
+    );
+}
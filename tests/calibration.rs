@@ -0,0 +1,173 @@
+use bmi160::{
+    AccelerometerRange, AxisRemap, Calibration, Face, GyroBiasEstimator, Sensor3DData,
+    Sensor3DDataScaled, SensorCalibration, SensorSelector, SignedAxis, SixPositionCalibration,
+    StillnessConfig,
+};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+
+// accel x = 1 g, y = -0.5 g, z = 0 g in the 2 g range
+const ACCEL: [u8; 6] = [0x00, 0x40, 0x00, 0xE0, 0x00, 0x00];
+
+fn calibration() -> Calibration {
+    Calibration {
+        accel: SensorCalibration {
+            bias: [0.5, 0.0, -1.0],
+            matrix: [[2.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+        },
+        ..Calibration::IDENTITY
+    }
+}
+
+#[test]
+fn identity_is_default() {
+    let imu = new_i2c(&[]);
+    assert_eq!(imu.calibration(), Calibration::IDENTITY);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn applies_calibration_before_remap() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], ACCEL.to_vec()),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], ACCEL.to_vec()),
+    ]);
+    imu.set_calibration(calibration());
+    imu.set_axis_remap(AxisRemap::Rotation {
+        x: SignedAxis::PosY,
+        y: SignedAxis::NegX,
+        z: SignedAxis::PosZ,
+    })
+    .unwrap();
+    let result = imu.data_scaled(SensorSelector::new().accel()).unwrap();
+    assert_eq!(
+        result.accel,
+        Some(Sensor3DDataScaled {
+            x: -0.5,
+            y: -1.0,
+            z: 1.0,
+        })
+    );
+    let raw = imu.data(SensorSelector::new().accel()).unwrap();
+    assert_eq!(
+        raw.accel,
+        Some(Sensor3DData {
+            x: -0x2000,
+            y: -0x4000,
+            z: 0,
+        })
+    );
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_restore_from_bytes() {
+    let bytes = calibration().to_bytes();
+    let mut imu = new_i2c(&[]);
+    imu.set_calibration(Calibration::from_bytes(&bytes).unwrap());
+    assert_eq!(imu.calibration(), calibration());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_capture_face_in_sensor_axes() {
+    let read = |data: [u8; 6]| I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], data.to_vec());
+    let mut imu = new_i2c(&[
+        read([0x00, 0x00, 0x00, 0x00, 0x00, 0xC0]),
+        read([0x00, 0x10, 0x00, 0x00, 0x00, 0xC0]),
+        read([0x00, 0x00, 0x00, 0x00, 0x00, 0xC0]),
+        read([0x10, 0x00, 0x00, 0x00, 0x00, 0xC0]),
+    ]);
+    imu.set_axis_remap(AxisRemap::Rotation {
+        x: SignedAxis::PosZ,
+        y: SignedAxis::PosX,
+        z: SignedAxis::PosY,
+    })
+    .unwrap();
+    let mut six_position = SixPositionCalibration::new(AccelerometerRange::G2, 2, 100);
+    let face = imu
+        .capture_accel_face(&mut six_position, &mut NoopDelay::new(), 10_000, 10)
+        .unwrap();
+    assert_eq!(face, Some(Face::ZDown));
+    assert!(six_position.is_captured(Face::ZDown));
+    destroy_i2c(imu);
+}
+
+#[test]
+fn gives_up_capturing_face() {
+    let read = I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], vec![0; 6]);
+    let mut imu = new_i2c(&[read.clone(), read]);
+    let mut six_position = SixPositionCalibration::new(AccelerometerRange::G2, 2, 100);
+    let face = imu
+        .capture_accel_face(&mut six_position, &mut NoopDelay::new(), 10_000, 2)
+        .unwrap();
+    assert_eq!(face, None);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_compensate_gyro_bias() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::OFFSET_0],
+            vec![0, 0, 0, 10, 0, 0, 0b1000_0000],
+        ),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 3, 5]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 4, 0xF6]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 5, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 6, 0b1000_1100]),
+    ]);
+    let offsets = imu.compensate_gyro_bias([0.305, 0.61, 0.0]).unwrap();
+    assert_eq!(offsets, [5, -10, 0]);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_compensate_accel_bias() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::OFFSET_0],
+            vec![10, 0, 0, 0, 0, 0, 0b0100_0000],
+        ),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0, 5]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 1, 0xF6]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 2, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::OFFSET_0 + 6, 0b0100_0000]),
+    ]);
+    let offsets = imu.compensate_accel_bias([0.0195, 0.039, 0.0]).unwrap();
+    assert_eq!(offsets, [5, -10, 0]);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn estimates_gyro_bias_in_sensor_axes() {
+    let gyro_accel = [&[0x29, 0x00, 0x00, 0x00, 0x00, 0x00][..], &ACCEL[..]].concat();
+    let read = I2cTrans::write_read(DEV_ADDR, vec![Register::GYR], gyro_accel);
+    let mut imu = new_i2c(&[read.clone(), read]);
+    imu.set_axis_remap(AxisRemap::Rotation {
+        x: SignedAxis::PosY,
+        y: SignedAxis::NegX,
+        z: SignedAxis::PosZ,
+    })
+    .unwrap();
+    imu.set_calibration(Calibration {
+        gyro: SensorCalibration {
+            bias: [1.0, 1.0, 1.0],
+            ..SensorCalibration::IDENTITY
+        },
+        ..calibration()
+    });
+    let mut estimator = GyroBiasEstimator::new(StillnessConfig {
+        window: 2,
+        ..StillnessConfig::default()
+    });
+    assert!(!imu.update_gyro_bias(&mut estimator).unwrap());
+    assert!(imu.update_gyro_bias(&mut estimator).unwrap());
+    let bias = estimator.bias().unwrap();
+    assert!((bias[0] - 2.5).abs() < 1e-4);
+    assert_eq!([bias[1], bias[2]], [0.0, 0.0]);
+    destroy_i2c(imu);
+}
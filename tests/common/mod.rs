@@ -12,10 +12,30 @@ impl Register {
     pub const MAG: u8 = 0x04;
     pub const GYR: u8 = 0x0C;
     pub const ACC: u8 = 0x12;
+    pub const SENSORTIME: u8 = 0x18;
     pub const STATUS: u8 = 0x1B;
+    pub const FIFO_LENGTH_0: u8 = 0x22;
+    pub const FIFO_DATA: u8 = 0x24;
     pub const CMD: u8 = 0x7E;
+    pub const ACC_CONF: u8 = 0x40;
     pub const ACC_RANGE: u8 = 0x41;
+    pub const GYR_CONF: u8 = 0x42;
     pub const GYR_RANGE: u8 = 0x43;
+    pub const MAG_CONF: u8 = 0x44;
+    pub const FIFO_CONFIG_0: u8 = 0x46;
+    pub const FIFO_CONFIG_1: u8 = 0x47;
+    pub const MAG_IF_0: u8 = 0x4B;
+    pub const MAG_IF_1: u8 = 0x4C;
+    pub const MAG_IF_2: u8 = 0x4D;
+    pub const MAG_IF_3: u8 = 0x4E;
+    pub const MAG_IF_4: u8 = 0x4F;
+    pub const INT_EN_1: u8 = 0x51;
+    pub const INT_OUT_CTRL: u8 = 0x53;
+    pub const INT_MAP_1: u8 = 0x56;
+    pub const IF_CONF: u8 = 0x6B;
+    pub const NV_CONF: u8 = 0x70;
+    pub const OFFSET_0: u8 = 0x71;
+    pub const SPI_COMM_TEST: u8 = 0x7F;
 }
 
 pub const DEV_ADDR: u8 = 0x68;
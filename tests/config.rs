@@ -0,0 +1,151 @@
+use bmi160::{
+    AccelerometerBandwidth, AccelerometerConfig, AccelerometerOdr, AccelerometerPowerMode,
+    AccelerometerRange, Config, Error, FifoConfig, GyroscopeBandwidth, GyroscopeConfig,
+    GyroscopeOdr, GyroscopePowerMode, GyroscopeRange, InterruptConfig, InterruptPin,
+    InterruptPinConfig, MagnetometerPowerMode, Offsets, SensorPowerMode,
+};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+
+fn config() -> Config {
+    Config {
+        power_mode: SensorPowerMode {
+            accel: AccelerometerPowerMode::LowPower,
+            gyro: GyroscopePowerMode::Normal,
+            magnet: MagnetometerPowerMode::Suspend,
+        },
+        accel_range: AccelerometerRange::G8,
+        gyro_range: GyroscopeRange::Scale500,
+        accel: AccelerometerConfig {
+            odr: AccelerometerOdr::Hz50,
+            bandwidth: AccelerometerBandwidth::Averaging(2),
+        },
+        gyro: GyroscopeConfig {
+            odr: GyroscopeOdr::Hz400,
+            bandwidth: GyroscopeBandwidth::Osr2,
+        },
+        fifo: FifoConfig {
+            accel: true,
+            gyro: true,
+            magnet: false,
+            time: true,
+            watermark: 100,
+        },
+        interrupts: InterruptConfig {
+            int1: InterruptPinConfig::default(),
+            int2: InterruptPinConfig {
+                output_enabled: true,
+                active_high: false,
+                open_drain: true,
+                edge_triggered: false,
+            },
+            data_ready: None,
+            fifo_watermark: Some(InterruptPin::Int1),
+            fifo_full: Some(InterruptPin::Int2),
+        },
+        offsets: Offsets {
+            accel_enabled: true,
+            accel: [-2, 0, 5],
+            gyro_enabled: false,
+            gyro: [-1, 256, 3],
+        },
+    }
+}
+
+const REGISTERS: [(u8, u8); 16] = [
+    (Register::ACC_CONF, 0b1010_0111),
+    (Register::ACC_RANGE, 0b0000_1000),
+    (Register::GYR_CONF, 0b0001_1010),
+    (Register::GYR_RANGE, 0b0000_0010),
+    (Register::FIFO_CONFIG_0, 100),
+    (Register::FIFO_CONFIG_1, 0b1101_0010),
+    (Register::INT_OUT_CTRL, 0b1100_1011),
+    (Register::INT_MAP_1, 0b0100_0010),
+    (Register::INT_EN_1, 0b0110_0000),
+    (Register::OFFSET_0, 0xFE),
+    (Register::OFFSET_0 + 1, 0),
+    (Register::OFFSET_0 + 2, 5),
+    (Register::OFFSET_0 + 3, 0xFF),
+    (Register::OFFSET_0 + 4, 0x00),
+    (Register::OFFSET_0 + 5, 0x03),
+    (Register::OFFSET_0 + 6, 0b0100_0111),
+];
+
+#[test]
+fn can_configure() {
+    let mut trans: Vec<_> = REGISTERS
+        .iter()
+        .map(|&(register, value)| I2cTrans::write(DEV_ADDR, vec![register, value]))
+        .collect();
+    trans.extend([
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0010]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0101]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_1000]),
+    ]);
+    let mut imu = new_i2c(&trans);
+    imu.configure(&config(), &mut NoopDelay::new()).unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_read_config() {
+    let value = |register| REGISTERS.iter().find(|r| r.0 == register).unwrap().1;
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::PMU_STATUS], vec![0b0010_0100]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0b0110_0000]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0b1100_1011]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_MAP_1], vec![0b0100_0010]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC_CONF],
+            (0..4).map(|i| value(Register::ACC_CONF + i)).collect(),
+        ),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::FIFO_CONFIG_0],
+            vec![100, 0b1101_0010],
+        ),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::OFFSET_0],
+            (0..7).map(|i| value(Register::OFFSET_0 + i)).collect(),
+        ),
+    ]);
+    assert_eq!(config(), imu.read_config().unwrap());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn cannot_configure_invalid_averaging() {
+    let mut imu = new_i2c(&[]);
+    let mut config = config();
+    config.accel.bandwidth = AccelerometerBandwidth::Averaging(8);
+    match imu.configure(&config, &mut NoopDelay::new()) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Invalid input data not reported."),
+    }
+    destroy_i2c(imu);
+}
+
+#[test]
+fn read_config_reports_reserved_values() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::PMU_STATUS], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_MAP_1], vec![0]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC_CONF],
+            vec![0x28, 0x0C, 0x28, 0],
+        ),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_CONFIG_0], vec![0, 0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::OFFSET_0], vec![0; 7]),
+    ]);
+    match imu.read_config() {
+        Err(Error::InvalidDeviceData) => (),
+        _ => panic!("Invalid device data not reported."),
+    }
+    destroy_i2c(imu);
+}
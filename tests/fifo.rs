@@ -0,0 +1,196 @@
+use bmi160::{
+    fifo::{FifoFrame, FifoFrames, FifoTiming, TimestampedFifo, FIFO_BUFFER_LEN},
+    AccelerometerOdr, AxisRemap, Error, FifoConfig, Sensor3DData, Sensor3DDataScaled, SignedAxis,
+};
+mod common;
+use crate::common::{destroy_i2c, destroy_spi, new_i2c, new_spi, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{i2c::Transaction as I2cTrans, spi::Transaction as SpiTrans};
+
+#[test]
+fn can_set_fifo_config() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_0, 25]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_1, 0b1101_0010]),
+    ]);
+    imu.set_fifo_config(FifoConfig {
+        accel: true,
+        gyro: true,
+        magnet: false,
+        time: true,
+        watermark: 25,
+    })
+    .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_set_fifo_config_magnet_only() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_0, 0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::FIFO_CONFIG_1, 0b0011_0000]),
+    ]);
+    imu.set_fifo_config(FifoConfig {
+        magnet: true,
+        ..Default::default()
+    })
+    .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_flush_fifo() {
+    let mut imu = new_i2c(&[I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0xB0])]);
+    imu.flush_fifo().unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_get_fifo_length() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::FIFO_LENGTH_0],
+        vec![0x34, 0b1111_1010],
+    )]);
+    assert_eq!(0x234, imu.fifo_length().unwrap());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_read_fifo() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_LENGTH_0], vec![3, 0]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::FIFO_DATA],
+            vec![0x40, 2, 0x48, 0x00, 0x80, 0x80, 0x80],
+        ),
+    ]);
+    let mut buffer = [0; 16];
+    let data = imu.read_fifo(&mut buffer).unwrap();
+    assert_eq!(&[0x40, 2, 0x48, 0x00, 0x80, 0x80, 0x80], data);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn read_fifo_is_limited_by_buffer() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_LENGTH_0], vec![20, 0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_DATA], vec![0x84, 1, 0, 2]),
+    ]);
+    let mut buffer = [0; 4];
+    let data = imu.read_fifo(&mut buffer).unwrap();
+    assert_eq!(&[0x84, 1, 0, 2], data);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_read_whole_fifo_with_spi() {
+    let mut fifo = vec![0; 1028];
+    fifo[0] = 0x84;
+    fifo[1027] = 0x80;
+    let mut imu = new_spi(&[
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(
+            vec![Register::FIFO_LENGTH_0 | 0x80, 0, 0],
+            vec![0, 0, 0b100],
+        ),
+        SpiTrans::transaction_end(),
+        SpiTrans::transaction_start(),
+        SpiTrans::write(Register::FIFO_DATA | 0x80),
+        SpiTrans::read_vec(fifo.clone()),
+        SpiTrans::transaction_end(),
+    ]);
+    let mut buffer = [0; FIFO_BUFFER_LEN];
+    let data = imu.read_fifo(&mut buffer).unwrap();
+    assert_eq!(&fifo[..], data);
+    destroy_spi(imu);
+}
+
+#[test]
+fn cannot_read_fifo_into_empty_buffer() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::FIFO_LENGTH_0],
+        vec![20, 0],
+    )]);
+    match imu.read_fifo(&mut []) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Invalid input data not reported."),
+    }
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_timestamp_fifo_samples() {
+    let fifo = vec![
+        0x84, 1, 0, 2, 0, 3, 0, // accel
+        0x84, 4, 0, 5, 0, 6, 0, // accel
+        0x44, 0x50, 0x34, 0x12, // sensor time
+    ];
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_LENGTH_0], vec![14, 0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::FIFO_DATA], fifo),
+    ]);
+    let mut buffer = [0; 32];
+    let data = imu.read_fifo(&mut buffer).unwrap();
+    let timing = FifoTiming::accel(AccelerometerOdr::Hz800, 0);
+    let mut samples = TimestampedFifo::new(data, timing);
+    let first = samples.next().unwrap();
+    assert_eq!(first.accel, Some(Sensor3DData { x: 1, y: 2, z: 3 }));
+    assert_eq!(first.time, Some(0x12_3420));
+    assert_eq!(samples.next().unwrap().time, Some(0x12_3440));
+    assert_eq!(samples.next(), None);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_parse_captured_dump() {
+    let dump = [
+        0x48, 0x04, // config change
+        0x9C, 1, 0, 2, 0, 3, 0, 0x34, 0x12, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0, 9, 0, // all
+        0x40, 3, // skip
+        0x84, 1, 0, 2, // incomplete accel
+    ];
+    let mut frames = FifoFrames::new(&dump);
+    assert_eq!(frames.next(), Some(FifoFrame::ConfigChange(0x04)));
+    match frames.next() {
+        Some(FifoFrame::Data(data)) => {
+            let magnet = data.magnet.unwrap();
+            assert_eq!(magnet.axes, Sensor3DData { x: 1, y: 2, z: 3 });
+            assert_eq!(magnet.hall_resistence, 0x1234);
+            assert_eq!(data.gyro, Some(Sensor3DData { x: 4, y: 5, z: 6 }));
+            assert_eq!(data.accel, Some(Sensor3DData { x: 7, y: 8, z: 9 }));
+            assert_eq!(data.time, None);
+        }
+        other => panic!("unexpected frame {:?}", other),
+    }
+    assert_eq!(frames.next(), Some(FifoFrame::Skip(3)));
+    assert_eq!(frames.next(), None);
+    assert_eq!(frames.remaining(), &[0x84, 1, 0, 2]);
+}
+
+#[test]
+fn can_scale_fifo_samples() {
+    let mut imu = new_i2c(&[]);
+    imu.set_axis_remap(AxisRemap::Rotation {
+        x: SignedAxis::PosY,
+        y: SignedAxis::NegX,
+        z: SignedAxis::PosZ,
+    })
+    .unwrap();
+    let frame = [0x84, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00];
+    let data = match FifoFrames::new(&frame).next() {
+        Some(FifoFrame::Data(data)) => data,
+        frame => panic!("Unexpected frame: {:?}", frame),
+    };
+    let scaled = imu.scale_data(data);
+    assert_eq!(
+        scaled.accel,
+        Some(Sensor3DDataScaled {
+            x: 0.0,
+            y: -1.0,
+            z: 0.0
+        })
+    );
+    destroy_i2c(imu);
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "fusion")]
+use bmi160::{
+    fusion::{Madgwick, Quaternion},
+    DataScaled, Sensor3DDataScaled,
+};
+
+// 10 ms in sensor time ticks
+const DT_TICKS: u32 = 256;
+
+fn v(x: f32, y: f32, z: f32) -> Sensor3DDataScaled {
+    Sensor3DDataScaled { x, y, z }
+}
+
+// Recorded trace: 1 s tilting, then 1 s turning around Z
+fn trace_sample(i: usize, magnet: bool) -> DataScaled {
+    let (gyro, accel, mag) = if i < 100 {
+        (v(10.0, -5.0, 0.0), v(0.2, -0.1, 0.95), v(20.0, 5.0, -40.0))
+    } else {
+        (v(0.0, 0.0, 45.0), v(0.0, 0.5, 0.85), v(-10.0, 25.0, -40.0))
+    };
+    DataScaled {
+        accel: Some(accel),
+        gyro: Some(gyro),
+        magnet: if magnet { Some(mag) } else { None },
+        time: None,
+    }
+}
+
+fn assert_near(q: Quaternion, expected: [f32; 4]) {
+    let actual = [q.w, q.x, q.y, q.z];
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+fn check_trace(magnet: bool, reference: [[f32; 4]; 4]) {
+    let mut filter = Madgwick::new(0.1);
+    for i in 0..200 {
+        filter.update(&trace_sample(i, magnet), DT_TICKS);
+        if (i + 1) % 50 == 0 {
+            assert_near(filter.quaternion(), reference[i / 50]);
+        }
+    }
+}
+
+#[test]
+fn matches_6_axis_reference_trace() {
+    check_trace(
+        false,
+        [
+            [0.998140, 0.011972, -0.059721, 0.002503],
+            [0.995085, 0.009314, -0.098104, 0.009733],
+            [0.975790, 0.036828, -0.082123, 0.199333],
+            [0.919895, 0.063273, -0.063550, 0.381774],
+        ],
+    );
+}
+
+#[test]
+fn matches_9_axis_reference_trace() {
+    check_trace(
+        true,
+        [
+            [0.998422, 0.007451, -0.055501, -0.004239],
+            [0.995546, 0.004406, -0.093915, -0.006999],
+            [0.981356, 0.024661, -0.105071, 0.159033],
+            [0.941484, 0.038527, -0.106272, 0.317537],
+        ],
+    );
+}
+
+#[test]
+fn converges_to_static_tilt() {
+    // Rolled 30° around X
+    let (sin, cos) = (0.5, 0.866_025_4);
+    let data = DataScaled {
+        accel: Some(v(0.0, sin, cos)),
+        gyro: Some(v(0.0, 0.0, 0.0)),
+        magnet: None,
+        time: None,
+    };
+    let mut filter = Madgwick::new(0.1);
+    for _ in 0..2000 {
+        filter.update_dt(&data, 0.01);
+    }
+    let q = filter.quaternion();
+    let angles = q.euler();
+    assert!((angles.roll - 30.0).abs() < 0.1);
+    assert!(angles.pitch.abs() < 0.1);
+    let gravity = q.gravity();
+    assert!(gravity.x.abs() < 2e-3);
+    assert!((gravity.y - sin).abs() < 2e-3);
+    assert!((gravity.z - cos).abs() < 2e-3);
+}
+
+#[test]
+fn integrates_yaw_rate() {
+    let data = DataScaled {
+        accel: Some(v(0.0, 0.0, 1.0)),
+        gyro: Some(v(0.0, 0.0, 90.0)),
+        magnet: None,
+        time: None,
+    };
+    let mut filter = Madgwick::new(0.1);
+    for _ in 0..50 {
+        filter.update(&data, DT_TICKS);
+    }
+    let angles = filter.quaternion().euler();
+    assert!((angles.yaw - 45.0).abs() < 0.01);
+    assert!(angles.roll.abs() < 0.01);
+    assert!(angles.pitch.abs() < 0.01);
+}
+
+#[test]
+fn ignores_data_without_gyro() {
+    let data = DataScaled {
+        accel: Some(v(0.0, 1.0, 0.0)),
+        gyro: None,
+        magnet: None,
+        time: None,
+    };
+    let mut filter = Madgwick::new(0.1);
+    filter.update(&data, DT_TICKS);
+    assert_eq!(filter.quaternion(), Quaternion::IDENTITY);
+}
+
+#[test]
+fn can_reset() {
+    let mut filter = Madgwick::new(0.1);
+    filter.update(&trace_sample(0, false), DT_TICKS);
+    assert_ne!(filter.quaternion(), Quaternion::IDENTITY);
+    filter.reset();
+    assert_eq!(filter.quaternion(), Quaternion::IDENTITY);
+}
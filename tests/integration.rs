@@ -1,4 +1,4 @@
-use bmi160::{Data, MagnetometerData, Sensor3DData, Sensor3DDataScaled, SensorSelector};
+use bmi160::{Data, Error, MagnetometerData, Sensor3DData, Sensor3DDataScaled, SensorSelector};
 mod common;
 use crate::common::{destroy_i2c, destroy_spi, new_i2c, new_spi, Register, DEV_ADDR};
 use embedded_hal_mock::eh1::i2c::Transaction as I2cTrans;
@@ -29,7 +29,10 @@ fn can_get_chip_id() {
 }
 
 mod get_sensor_data {
-    use bmi160::DataScaled;
+    use bmi160::{
+        DataMilli, DataScaled, DataSi, MetersPerSecondSquared, RadiansPerSecond, Sensor3DDataMilli,
+        Sensor3DDataSi,
+    };
 
     use super::*;
 
@@ -139,14 +142,7 @@ mod get_sensor_data {
         )]);
         let result = imu.data_scaled(SensorSelector::all()).unwrap();
         let expected = DataScaled {
-            magnet: Some(MagnetometerData {
-                axes: Sensor3DData {
-                    x: 0x0201,
-                    y: 0x0403,
-                    z: 0x0605,
-                },
-                hall_resistence: 0x0807,
-            }),
+            magnet: None,
             gyro: Some(Sensor3DDataScaled {
                 x: 0x0A09 as f32 * (1. / 16.4),
                 y: 0x0C0B as f32 * (1. / 16.4),
@@ -162,4 +158,159 @@ mod get_sensor_data {
         assert_eq!(result, expected);
         destroy_i2c(imu);
     }
+
+    #[test]
+    fn all_si() {
+        let mut imu = new_i2c(&[I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::MAG],
+            BUFFER.to_vec(),
+        )]);
+        let result = imu.data_si(SensorSelector::all()).unwrap();
+        let gyro = |v: i16| RadiansPerSecond((v as f32 * (1. / 16.4)).to_radians());
+        let accel = |v: i16| MetersPerSecondSquared(v as f32 * (1. / 16384.) * 9.80665);
+        let expected = DataSi {
+            magnet: None,
+            gyro: Some(Sensor3DDataSi {
+                x: gyro(0x0A09),
+                y: gyro(0x0C0B),
+                z: gyro(0x0E0D),
+            }),
+            accel: Some(Sensor3DDataSi {
+                x: accel(0x100F),
+                y: accel(0x1211),
+                z: accel(0x1413),
+            }),
+            time: Some(0x171615),
+        };
+        assert_eq!(result, expected);
+        destroy_i2c(imu);
+    }
+
+    #[test]
+    fn all_milli_skips_magnet() {
+        let mut imu = new_i2c(&[I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::GYR],
+            BUFFER[8..].to_vec(),
+        )]);
+        let result = imu.data_milli(SensorSelector::all()).unwrap();
+        let expected = DataMilli {
+            gyro: Some(Sensor3DDataMilli {
+                x: 156_646,
+                y: 187_988,
+                z: 219_329,
+            }),
+            accel: Some(Sensor3DDataMilli {
+                x: 251,
+                y: 282,
+                z: 314,
+            }),
+            time: Some(0x171615),
+        };
+        assert_eq!(result, expected);
+        destroy_i2c(imu);
+    }
+
+    #[test]
+    fn can_extend_time_across_reads() {
+        use bmi160::{SensorTime, SensorTimeExtender};
+        let read = |time: [u8; 3]| {
+            let mut data = vec![0; 6];
+            data.extend_from_slice(&time);
+            I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], data)
+        };
+        let mut imu = new_i2c(&[read([0xF0, 0xFF, 0xFF]), read([0x10, 0x00, 0x00])]);
+        let mut extender = SensorTimeExtender::new();
+        let selector = SensorSelector::new().accel().time();
+        let first = imu.data(selector).unwrap().time.unwrap();
+        assert_eq!(extender.extend(SensorTime(first)), 0xFF_FFF0);
+        let second = imu.data(selector).unwrap().time.unwrap();
+        assert_eq!(extender.extend(SensorTime(second)), 0x100_0010);
+        assert_eq!(SensorTime(second).wrapping_sub(SensorTime(first)), 0x20);
+        destroy_i2c(imu);
+    }
+
+    #[test]
+    fn can_sync_clock() {
+        use bmi160::{ClockSync, SensorTime};
+        let mut imu = new_i2c(&[I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::SENSORTIME],
+            vec![0x00, 0x01, 0x00],
+        )]);
+        let mut sync = ClockSync::<4>::new();
+        let mut host = 5_000;
+        let time = imu
+            .sync_clock(&mut sync, || {
+                host += 100;
+                host
+            })
+            .unwrap();
+        assert_eq!(time, SensorTime(0x100));
+        let converted = sync.to_host(SensorTime(0x200)).unwrap();
+        assert_eq!(converted.micros, 5_150 + 10_000);
+        assert_eq!(converted.error_micros, 50.0);
+        destroy_i2c(imu);
+    }
+
+    #[test]
+    fn can_read_samples() {
+        use bmi160::SampleReport;
+        let status = |value| I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![value]);
+        let read = |x: u8, time: [u8; 3]| {
+            let mut data = vec![x, 0, 0, 0, 0, 0];
+            data.extend_from_slice(&time);
+            I2cTrans::write_read(DEV_ADDR, vec![Register::ACC], data)
+        };
+        let mut imu = new_i2c(&[
+            status(0x80),
+            read(1, [0x10, 0x01, 0x00]),
+            status(0x00),
+            status(0x80),
+            read(1, [0x20, 0x01, 0x00]),
+            status(0x80),
+            read(2, [0x10, 0x03, 0x00]),
+            status(0x00),
+            status(0x00),
+        ]);
+        let mut samples = [Data {
+            accel: None,
+            gyro: None,
+            magnet: None,
+            time: None,
+        }; 3];
+        let report = imu
+            .read_samples(SensorSelector::new().accel(), &mut samples, 256, 2)
+            .unwrap();
+        assert_eq!(
+            report,
+            SampleReport {
+                count: 2,
+                duplicates: 1,
+                missed: 1,
+            }
+        );
+        assert_eq!(samples[1].accel.unwrap().x, 2);
+        assert_eq!(samples[1].time, Some(0x310));
+        destroy_i2c(imu);
+    }
+
+    #[test]
+    fn cannot_read_samples_with_invalid_period() {
+        let mut imu = new_i2c(&[]);
+        let mut samples = [Data {
+            accel: None,
+            gyro: None,
+            magnet: None,
+            time: None,
+        }; 1];
+        for period_ticks in [0, 3, 1 << 24] {
+            match imu.read_samples(SensorSelector::new().accel(), &mut samples, period_ticks, 1) {
+                Err(Error::InvalidInputData) => (),
+                _ => panic!(),
+            }
+        }
+        destroy_i2c(imu);
+    }
 }
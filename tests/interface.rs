@@ -0,0 +1,109 @@
+use bmi160::{Error, I2cWatchdog, InterfaceConfig};
+mod common;
+use crate::common::{destroy_i2c, destroy_spi, new_i2c, new_spi, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{i2c::Transaction as I2cTrans, spi::Transaction as SpiTrans};
+
+#[test]
+fn can_init_i2c() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::CHIPID],
+        vec![0xD1],
+    )]);
+    imu.init().unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_init_spi() {
+    let mut imu = new_spi(&[
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(vec![Register::SPI_COMM_TEST | 0x80, 0], vec![0, 0]),
+        SpiTrans::transaction_end(),
+        SpiTrans::transaction_start(),
+        SpiTrans::transfer_in_place(vec![Register::CHIPID | 0x80, 0], vec![0, 0xD1]),
+        SpiTrans::transaction_end(),
+    ]);
+    imu.init().unwrap();
+    destroy_spi(imu);
+}
+
+#[test]
+fn init_reports_invalid_chip_id() {
+    let mut imu = new_i2c(&[I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::CHIPID],
+        vec![0xD3],
+    )]);
+    match imu.init() {
+        Err(Error::InvalidChipId(0xD3)) => (),
+        _ => panic!("Invalid chip ID not reported."),
+    }
+    destroy_i2c(imu);
+}
+
+macro_rules! get_config_test {
+    ($name:ident, $if_conf:expr, $nv_conf:expr, $spi_3wire:expr, $wdt:ident) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&[
+                I2cTrans::write_read(DEV_ADDR, vec![Register::IF_CONF], vec![$if_conf]),
+                I2cTrans::write_read(DEV_ADDR, vec![Register::NV_CONF], vec![$nv_conf]),
+            ]);
+            let config = imu.interface_config().unwrap();
+            assert_eq!(
+                InterfaceConfig {
+                    spi_3wire: $spi_3wire,
+                    i2c_watchdog: I2cWatchdog::$wdt,
+                },
+                config
+            );
+            destroy_i2c(imu);
+        }
+    };
+}
+
+get_config_test!(get_config_default, 0, 0, false, Disabled);
+get_config_test!(get_config_3wire, 0b0010_0001, 0b0000_0010, true, Disabled);
+get_config_test!(get_config_wdt_1ms, 0b0010_0000, 0b0000_0100, false, Ms1);
+get_config_test!(get_config_wdt_50ms, 0, 0b0000_0111, false, Ms50);
+
+macro_rules! set_config_test {
+    ($name:ident, $spi_3wire:expr, $wdt:ident, $if_before:expr, $if_conf:expr, $nv_before:expr, $nv_conf:expr) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&[
+                I2cTrans::write_read(DEV_ADDR, vec![Register::IF_CONF], vec![$if_before]),
+                I2cTrans::write(DEV_ADDR, vec![Register::IF_CONF, $if_conf]),
+                I2cTrans::write_read(DEV_ADDR, vec![Register::NV_CONF], vec![$nv_before]),
+                I2cTrans::write(DEV_ADDR, vec![Register::NV_CONF, $nv_conf]),
+            ]);
+            imu.set_interface_config(InterfaceConfig {
+                spi_3wire: $spi_3wire,
+                i2c_watchdog: I2cWatchdog::$wdt,
+            })
+            .unwrap();
+            destroy_i2c(imu);
+        }
+    };
+}
+
+set_config_test!(
+    set_config_3wire,
+    true,
+    Disabled,
+    0b0010_0000,
+    0b0010_0001,
+    0b0000_0111,
+    0b0000_0001
+);
+set_config_test!(
+    set_config_4wire,
+    false,
+    Ms1,
+    0b0010_0001,
+    0b0010_0000,
+    0b0000_0001,
+    0b0000_0101
+);
+set_config_test!(set_config_wdt_50ms, false, Ms50, 0, 0, 0, 0b0000_0110);
@@ -0,0 +1,88 @@
+use bmi160::{InterruptPin, InterruptPinConfig, InterruptSource};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::i2c::Transaction as I2cTrans;
+
+#[test]
+fn can_set_int1_pin_config() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0b0101_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_OUT_CTRL, 0b0101_1011]),
+    ]);
+    imu.set_interrupt_pin_config(InterruptPin::Int1, InterruptPinConfig::default())
+        .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_set_int2_pin_config() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_OUT_CTRL], vec![0b1111_1010]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_OUT_CTRL, 0b1100_1010]),
+    ]);
+    let config = InterruptPinConfig {
+        output_enabled: true,
+        active_high: false,
+        open_drain: true,
+        edge_triggered: false,
+    };
+    imu.set_interrupt_pin_config(InterruptPin::Int2, config)
+        .unwrap();
+    destroy_i2c(imu);
+}
+
+macro_rules! enable_test {
+    ($name:ident, $source:ident, $pin:ident, $map_before:expr, $map:expr, $en:expr) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&[
+                I2cTrans::write_read(DEV_ADDR, vec![Register::INT_MAP_1], vec![$map_before]),
+                I2cTrans::write(DEV_ADDR, vec![Register::INT_MAP_1, $map]),
+                I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0b0000_0001]),
+                I2cTrans::write(DEV_ADDR, vec![Register::INT_EN_1, $en]),
+            ]);
+            imu.enable_interrupt(InterruptSource::$source, InterruptPin::$pin)
+                .unwrap();
+            destroy_i2c(imu);
+        }
+    };
+}
+
+enable_test!(
+    drdy_int1,
+    DataReady,
+    Int1,
+    0b0000_1000,
+    0b1000_0000,
+    0b0001_0001
+);
+enable_test!(
+    drdy_int2,
+    DataReady,
+    Int2,
+    0b1000_0000,
+    0b0000_1000,
+    0b0001_0001
+);
+enable_test!(fwm_int1, FifoWatermark, Int1, 0, 0b0100_0000, 0b0100_0001);
+enable_test!(fwm_int2, FifoWatermark, Int2, 0, 0b0000_0100, 0b0100_0001);
+enable_test!(
+    ffull_int1,
+    FifoFull,
+    Int1,
+    0b0000_0010,
+    0b0010_0000,
+    0b0010_0001
+);
+enable_test!(ffull_int2, FifoFull, Int2, 0, 0b0000_0010, 0b0010_0001);
+
+#[test]
+fn can_disable_interrupt() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::INT_EN_1], vec![0b0111_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::INT_EN_1, 0b0011_0000]),
+    ]);
+    imu.disable_interrupt(InterruptSource::FifoWatermark)
+        .unwrap();
+    destroy_i2c(imu);
+}
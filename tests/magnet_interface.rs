@@ -0,0 +1,139 @@
+use bmi160::{Error, MagnetometerConfig, MagnetometerOdr, MagnetometerReadBurst};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::i2c::Transaction as I2cTrans;
+
+#[test]
+fn can_enable_magnet_interface() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::IF_CONF], vec![0b0001_0001]),
+        I2cTrans::write(DEV_ADDR, vec![Register::IF_CONF, 0b0010_0001]),
+    ]);
+    imu.enable_magnet_interface().unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_set_magnet_interface_address() {
+    let mut imu = new_i2c(&[I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_0, 0x20])]);
+    imu.set_magnet_interface_address(0x10).unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn cannot_set_invalid_magnet_interface_address() {
+    let mut imu = new_i2c(&[]);
+    match imu.set_magnet_interface_address(0x80) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Invalid input data not reported."),
+    }
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_enable_manual_mode() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b0000_0011]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b1000_0011]),
+    ]);
+    imu.enable_magnet_manual_mode().unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_disable_manual_mode() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1000_0011]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b0000_0011]),
+    ]);
+    imu.disable_magnet_manual_mode().unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_read_magnet_register() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, 0x40]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![1 << 2]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG], vec![0x32]),
+    ]);
+    let value = imu.read_magnet_register(0x40).unwrap();
+    assert_eq!(0x32, value);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_write_magnet_register() {
+    let mut imu = new_i2c(&[
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_4, 0x01]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_3, 0x4B]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+    ]);
+    imu.write_magnet_register(0x4B, 0x01).unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn reports_magnet_register_access_timeout() {
+    let mut transactions = vec![I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, 0x40])];
+    transactions.extend(
+        (0..1000).map(|_| I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![1 << 2])),
+    );
+    let mut imu = new_i2c(&transactions);
+    match imu.read_magnet_register(0x40) {
+        Err(Error::Timeout) => (),
+        _ => panic!("Timeout not reported."),
+    }
+    destroy_i2c(imu);
+}
+
+macro_rules! set_config_test {
+    ($name:ident, $odr:ident, $burst:ident, $offset:expr, $mag_conf:expr, $mag_if_1:expr) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&[
+                I2cTrans::write(DEV_ADDR, vec![Register::MAG_CONF, $mag_conf]),
+                I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1011_1110]),
+                I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, $mag_if_1]),
+            ]);
+            imu.set_magnet_config(MagnetometerConfig {
+                odr: MagnetometerOdr::$odr,
+                read_burst: MagnetometerReadBurst::$burst,
+                offset: $offset,
+            })
+            .unwrap();
+            destroy_i2c(imu);
+        }
+    };
+}
+
+set_config_test!(config_0_78hz, Hz0_78, Bytes1, 15, 0b0001, 0b1011_1100);
+set_config_test!(config_12_5hz, Hz12_5, Bytes2, 3, 0b0101, 0b1000_1101);
+set_config_test!(config_25hz, Hz25, Bytes6, 0, 0b0110, 0b1000_0010);
+set_config_test!(config_200hz, Hz200, Bytes8, 1, 0b1001, 0b1000_0111);
+set_config_test!(config_800hz, Hz800, Bytes8, 0, 0b1011, 0b1000_0011);
+
+macro_rules! invalid_config_test {
+    ($name:ident, $odr:ident, $offset:expr) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&[]);
+            let result = imu.set_magnet_config(MagnetometerConfig {
+                odr: MagnetometerOdr::$odr,
+                read_burst: MagnetometerReadBurst::Bytes8,
+                offset: $offset,
+            });
+            match result {
+                Err(Error::InvalidInputData) => (),
+                _ => panic!("Invalid input data not reported."),
+            }
+            destroy_i2c(imu);
+        }
+    };
+}
+
+invalid_config_test!(invalid_offset, Hz0_78, 16);
+invalid_config_test!(offset_above_200hz_period, Hz200, 2);
+invalid_config_test!(offset_above_400hz_period, Hz400, 1);
+invalid_config_test!(offset_above_800hz_period, Hz800, 1);
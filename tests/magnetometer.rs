@@ -0,0 +1,197 @@
+use bmi160::{
+    magnetometer::{Ak09916, Bmm150},
+    Bmm150Preset, MagnetometerOdr, SensorSelector,
+};
+#[cfg(feature = "calibration")]
+use bmi160::{AxisRemap, Calibration, Error, MagnetCalibrator, SensorCalibration, SignedAxis};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+
+fn write_magnet_register(register: u8, data: u8) -> Vec<I2cTrans> {
+    vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_4, data]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_3, register]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+    ]
+}
+
+fn read_magnet_register(register: u8, data: u8) -> Vec<I2cTrans> {
+    vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, register]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG], vec![data]),
+    ]
+}
+
+const TRIM: [u8; 21] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 26, 26, 0x00, 0x00, 0xFB, 0x02, 0xAB, 0x60, 0xAA,
+    0x19, 0x00, 0x00, 0xFD, 0x1D,
+];
+
+fn enter_setup_mode(address: u8) -> Vec<I2cTrans> {
+    vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_1001]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::IF_CONF], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::IF_CONF, 0b0010_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_0, address << 1]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b1000_0000]),
+    ]
+}
+
+fn enter_data_mode(data_register: u8, odr: u8) -> Vec<I2cTrans> {
+    vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_2, data_register]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_CONF, odr]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1000_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b1000_0011]),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::MAG_IF_1], vec![0b1000_0011]),
+        I2cTrans::write(DEV_ADDR, vec![Register::MAG_IF_1, 0b0000_0011]),
+    ]
+}
+
+fn setup_transactions(rep_xy: u8, rep_z: u8, odr: u8) -> Vec<I2cTrans> {
+    let mut trans = enter_setup_mode(0x10);
+    trans.extend(write_magnet_register(0x4B, 0x01));
+    trans.extend(write_magnet_register(0x51, rep_xy));
+    trans.extend(write_magnet_register(0x52, rep_z));
+    for (i, data) in TRIM.iter().enumerate() {
+        trans.extend(read_magnet_register(0x5D + i as u8, *data));
+    }
+    trans.extend(write_magnet_register(0x4C, 0x02));
+    trans.extend(enter_data_mode(0x42, odr));
+    trans
+}
+
+macro_rules! setup_test {
+    ($name:ident, $preset:ident, $rep_xy:expr, $rep_z:expr, $odr:expr) => {
+        #[test]
+        fn $name() {
+            let mut imu = new_i2c(&setup_transactions($rep_xy, $rep_z, $odr));
+            imu.setup_bmm150(&mut NoopDelay::new(), Bmm150Preset::$preset)
+                .unwrap();
+            destroy_i2c(imu);
+        }
+    };
+}
+
+setup_test!(setup_low_power, LowPower, 0x01, 0x02, 0b0101);
+setup_test!(setup_regular, Regular, 0x04, 0x0E, 0b0101);
+setup_test!(setup_enhanced_regular, EnhancedRegular, 0x07, 0x1A, 0b0101);
+setup_test!(setup_high_accuracy, HighAccuracy, 0x17, 0x52, 0b0110);
+
+#[test]
+fn can_get_compensated_data() {
+    let mut trans = setup_transactions(0x04, 0x0E, 0b0101);
+    let x = (120_i16 << 3).to_le_bytes();
+    let y = (-200_i16 << 3).to_le_bytes();
+    let z = (300_i16 << 1).to_le_bytes();
+    let rhall = (6500_u16 << 2).to_le_bytes();
+    trans.push(I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::MAG],
+        vec![x[0], x[1], y[0], y[1], z[0], z[1], rhall[0], rhall[1]],
+    ));
+    let mut imu = new_i2c(&trans);
+    imu.setup_bmm150(&mut NoopDelay::new(), Bmm150Preset::Regular)
+        .unwrap();
+    let magnet = imu
+        .data_scaled(SensorSelector::new().magnet())
+        .unwrap()
+        .magnet
+        .unwrap();
+    assert!((magnet.x - 43.646_873).abs() < 0.001);
+    assert!((magnet.y + 72.744_79).abs() < 0.001);
+    assert!((magnet.z - 108.323_1).abs() < 0.001);
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_setup_bmm150_as_aux_magnetometer() {
+    let mut imu = new_i2c(&setup_transactions(0x17, 0x52, 0b0110));
+    imu.setup_magnetometer(
+        &Bmm150::new(Bmm150Preset::HighAccuracy),
+        &mut NoopDelay::new(),
+    )
+    .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_setup_ak09916_and_get_data() {
+    let mut trans = enter_setup_mode(0x0C);
+    trans.extend(write_magnet_register(0x32, 0x01));
+    trans.extend(write_magnet_register(0x31, 0x00));
+    trans.extend(write_magnet_register(0x31, 0x01));
+    trans.extend(enter_data_mode(0x11, 0b1000));
+    trans.push(I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::MAG],
+        vec![0x64, 0x00, 0x38, 0xFF, 0x2C, 0x01, 0x00, 0x00],
+    ));
+    let mut imu = new_i2c(&trans);
+    imu.setup_magnetometer(&Ak09916::new(MagnetometerOdr::Hz100), &mut NoopDelay::new())
+        .unwrap();
+    let magnet = imu
+        .data_scaled(SensorSelector::new().magnet())
+        .unwrap()
+        .magnet
+        .unwrap();
+    assert!((magnet.x - 15.0).abs() < 0.001);
+    assert!((magnet.y + 30.0).abs() < 0.001);
+    assert!((magnet.z - 45.0).abs() < 0.001);
+    destroy_i2c(imu);
+}
+
+#[test]
+#[cfg(feature = "calibration")]
+fn collects_calibration_samples_in_sensor_axes() {
+    let mut trans = enter_setup_mode(0x0C);
+    trans.extend(write_magnet_register(0x32, 0x01));
+    trans.extend(write_magnet_register(0x31, 0x00));
+    trans.extend(write_magnet_register(0x31, 0x01));
+    trans.extend(enter_data_mode(0x11, 0b1000));
+    let read = I2cTrans::write_read(
+        DEV_ADDR,
+        vec![Register::MAG],
+        vec![0x64, 0x00, 0x38, 0xFF, 0x2C, 0x01, 0x00, 0x00],
+    );
+    trans.push(read.clone());
+    trans.push(read);
+    let mut imu = new_i2c(&trans);
+    imu.setup_magnetometer(&Ak09916::new(MagnetometerOdr::Hz100), &mut NoopDelay::new())
+        .unwrap();
+    imu.set_axis_remap(AxisRemap::Rotation {
+        x: SignedAxis::PosY,
+        y: SignedAxis::NegX,
+        z: SignedAxis::PosZ,
+    })
+    .unwrap();
+    imu.set_calibration(Calibration {
+        magnet: SensorCalibration {
+            bias: [100.0, 0.0, 0.0],
+            ..SensorCalibration::IDENTITY
+        },
+        ..Calibration::IDENTITY
+    });
+    let mut calibrator = MagnetCalibrator::<4>::new(1.0);
+    assert!(imu.collect_magnet_sample(&mut calibrator).unwrap());
+    // Same sample is rejected as too close
+    assert!(!imu.collect_magnet_sample(&mut calibrator).unwrap());
+    assert_eq!(calibrator.len(), 1);
+    destroy_i2c(imu);
+}
+
+#[test]
+#[cfg(feature = "calibration")]
+fn cannot_collect_calibration_samples_without_magnetometer() {
+    let mut imu = new_i2c(&[]);
+    let mut calibrator = MagnetCalibrator::<4>::new(1.0);
+    assert!(matches!(
+        imu.collect_magnet_sample(&mut calibrator),
+        Err(Error::InvalidInputData)
+    ));
+    destroy_i2c(imu);
+}
@@ -0,0 +1,80 @@
+use bmi160::{DecodedRegister, GyroscopeRange, RegisterDump};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+
+fn register_values() -> [u8; 128] {
+    core::array::from_fn(|i| i as u8 ^ 0x5A)
+}
+
+#[test]
+fn can_dump_registers() {
+    let values = register_values();
+    let mut imu = new_i2c(&[
+        I2cTrans::write_read(DEV_ADDR, vec![0x00], values[..=0x23].to_vec()),
+        I2cTrans::write_read(DEV_ADDR, vec![0x25], values[0x25..].to_vec()),
+    ]);
+    let dump = imu.dump_registers().unwrap();
+    let mut expected = values;
+    expected[0x24] = 0;
+    assert_eq!(&expected, dump.registers());
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_restore_registers() {
+    let mut values = register_values();
+    values[usize::from(Register::GYR_RANGE)] = GyroscopeRange::Scale125 as u8;
+    let writable = (0x40..=0x47)
+        .chain(0x4B..=0x4D)
+        .chain(0x50..=0x6C)
+        .chain(0x70..=0x77)
+        .chain(0x7A..=0x7B);
+    let trans: Vec<_> = writable
+        .map(|register: u8| {
+            I2cTrans::write(DEV_ADDR, vec![register, values[usize::from(register)]])
+        })
+        .collect();
+    assert_eq!(50, trans.len());
+    let mut imu = new_i2c(&trans);
+    imu.restore_registers(&RegisterDump::from(values), &mut NoopDelay::new())
+        .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn does_not_restore_mag_if_2_in_manual_mode() {
+    let mut values = register_values();
+    values[usize::from(Register::MAG_IF_1)] = 0b1000_0011;
+    let writable = (0x40..=0x47)
+        .chain(0x4B..=0x4C)
+        .chain(0x50..=0x6C)
+        .chain(0x70..=0x77)
+        .chain(0x7A..=0x7B);
+    let trans: Vec<_> = writable
+        .map(|register: u8| {
+            I2cTrans::write(DEV_ADDR, vec![register, values[usize::from(register)]])
+        })
+        .collect();
+    assert_eq!(49, trans.len());
+    let mut imu = new_i2c(&trans);
+    imu.restore_registers(&RegisterDump::from(values), &mut NoopDelay::new())
+        .unwrap();
+    destroy_i2c(imu);
+}
+
+#[test]
+fn can_print_dump() {
+    let mut values = [0; 128];
+    values[0] = 0xD1;
+    values[usize::from(Register::GYR_RANGE)] = 0x04;
+    let dump = RegisterDump::from(values);
+    let gyr_range = dump.entries().find(|e| e.name == "GYR_RANGE").unwrap();
+    assert_eq!(
+        DecodedRegister::GyroRange(GyroscopeRange::Scale125),
+        gyr_range.decoded
+    );
+    let text = format!("{:?}", dump);
+    assert!(text.contains("name: \"CHIP_ID\", address: 0, value: 209, decoded: NotDecoded"));
+    assert!(text.contains("GyroRange(Scale125)"));
+}
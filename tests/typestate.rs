@@ -0,0 +1,94 @@
+use bmi160::{Sensor3DData, Sensor3DDataScaled};
+mod common;
+use crate::common::{destroy_i2c, new_i2c, Register, DEV_ADDR};
+use embedded_hal_mock::eh1::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+
+fn suspend_transactions() -> Vec<I2cTrans> {
+    vec![
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0100]),
+    ]
+}
+
+#[test]
+fn can_convert_and_go_back() {
+    let imu = new_i2c(&suspend_transactions());
+    let imu = imu.into_typestate().unwrap();
+    destroy_i2c(imu.into_inner());
+}
+
+#[test]
+fn can_read_accel_when_powered() {
+    let mut trans = suspend_transactions();
+    trans.extend([
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0001]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC],
+            vec![0x00, 0x20, 0x00, 0xE0, 0x00, 0x40],
+        ),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::ACC],
+            vec![0x01, 0x00, 0x02, 0x00, 0x03, 0x00],
+        ),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0000]),
+    ]);
+    let mut delay = NoopDelay::new();
+    let imu = new_i2c(&trans).into_typestate().unwrap();
+    let mut imu = imu.into_accel_normal(&mut delay).unwrap();
+    assert_eq!(
+        Sensor3DDataScaled {
+            x: 0.5,
+            y: -0.5,
+            z: 1.0
+        },
+        imu.accel_scaled().unwrap()
+    );
+    assert_eq!(Sensor3DData { x: 1, y: 2, z: 3 }, imu.accel().unwrap());
+    let imu = imu.into_accel_suspend().unwrap();
+    destroy_i2c(imu.into_inner());
+}
+
+#[test]
+fn can_read_gyro_and_data_when_powered() {
+    let mut trans = suspend_transactions();
+    trans.extend([
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0111]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0101]),
+        I2cTrans::write(DEV_ADDR, vec![Register::GYR_RANGE, 0b0000_0100]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::GYR],
+            vec![0x01, 0x00, 0x02, 0x00, 0x03, 0x00],
+        ),
+        I2cTrans::write_read(DEV_ADDR, vec![Register::ACC_CONF], vec![0x28]),
+        I2cTrans::write(DEV_ADDR, vec![Register::ACC_CONF, 0b1010_1000]),
+        I2cTrans::write(DEV_ADDR, vec![Register::CMD, 0b0001_0010]),
+        I2cTrans::write_read(
+            DEV_ADDR,
+            vec![Register::GYR],
+            vec![
+                0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00, 0x05, 0x00, 0x06, 0x00, 0x07, 0x08,
+                0x09,
+            ],
+        ),
+    ]);
+    let mut delay = NoopDelay::new();
+    let imu = new_i2c(&trans).into_typestate().unwrap();
+    let imu = imu.into_gyro_fast_startup().unwrap();
+    let mut imu = imu.into_gyro_normal(&mut delay).unwrap();
+    imu.set_gyro_range(bmi160::GyroscopeRange::Scale125)
+        .unwrap();
+    let gyro = imu.gyro_scaled().unwrap();
+    assert_eq!(
+        (1. / 262.4, 2. / 262.4, 3. / 262.4),
+        (gyro.x, gyro.y, gyro.z)
+    );
+    let mut imu = imu.into_accel_low_power(2, &mut delay).unwrap();
+    let data = imu.data().unwrap();
+    assert_eq!(Some(Sensor3DData { x: 4, y: 5, z: 6 }), data.accel);
+    assert_eq!(Some(Sensor3DData { x: 1, y: 2, z: 3 }), data.gyro);
+    assert_eq!(Some(0x090807), data.time);
+    destroy_i2c(imu.into_inner());
+}